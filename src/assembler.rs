@@ -1,37 +1,78 @@
-mod instruction;
+pub mod instruction;
 mod lexical_analyzer;
 mod peek_wrapper;
-mod gen_errors;
+pub mod diagnostic;
 mod insertable_num;
 mod stack;
+mod object_code;
+mod listing;
+pub mod symbol_file;
+mod json;
+mod memory_map;
+mod debug_info;
+pub mod warning;
+pub mod cpu;
+pub mod options;
+pub mod output_format;
+pub mod disassembler;
+pub mod simulator;
+pub mod test_runner;
 
 // crate imports 
 use crate::assembler::lexical_analyzer::LexicalAnalyzer;
-use crate::assembler::instruction::Instruction;
-use crate::assembler::lexical_analyzer::TokenType;
-use crate::assembler::lexical_analyzer::Token;
+use crate::assembler::instruction::{AddressingMode, Instruction, Opcode};
+pub use crate::assembler::lexical_analyzer::TokenType;
+pub use crate::assembler::lexical_analyzer::Token;
 use crate::assembler::peek_wrapper::PeekWrapper;
-use crate::assembler::gen_errors::GeneralError;
+use crate::assembler::diagnostic::{Diagnostic, Severity, codes};
 use crate::assembler::lexical_analyzer::LexicalIterator;
 use crate::assembler::insertable_num::InsertableNum;
 use crate::assembler::stack::*;
+use crate::assembler::object_code::ObjectCode;
+use crate::assembler::options::AssemblerOptions;
+use crate::assembler::listing::SourceLine;
+use crate::assembler::symbol_file::Symbol;
+use crate::assembler::memory_map::MemoryMap;
+use crate::assembler::output_format::OutputFormat;
+use crate::assembler::warning::{Warning, WarningLevel};
+use crate::assembler::cpu::Cpu;
+use crate::assembler::test_runner::TestBlock;
 
 // std imports
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::Path;
 use std::u8;
 use std::u16;
 
+// branches this close to the limit get
+// a warning, a few more bytes and they break
+const BRANCH_MARGIN: i32 = 8;
+
 // holds the assembler main struct 
 pub struct Assembler
 {
     read_file_name: String,
     lexical_iterator: PeekWrapper<LexicalIterator>,
     symbol_table: HashMap<String,InsertableNum>,  
+    constants: HashSet<String>,         // labels set with =, they hold a value instead of an address
+    symbol_lines: HashMap<String,(u32,u32,u32)>,  // the file line, column and length each label was defined at
     current_byte: u32,
+    cpu: Cpu,                           // the cpu set by .CPU at this point in the file
+    accumulator_16: bool,               // .A16, the 65816 accumulator is 16 bits
+    index_16: bool,                     // .I16, the 65816 X and Y are 16 bits
     instruction_table: HashMap<String,Instruction>,
-    file_writer: File
+    extra_opcodes: Vec<Opcode>,         // rows from the user's instruction table
+    file_writer: Option<File>,         // none when only the object code is wanted
+    object_code: ObjectCode,
+    options: AssemblerOptions,
+    source_lines: Vec<SourceLine>,      // what each line emitted on the second pass
+    diagnostics: Vec<Diagnostic>,       // every error and warning, reported at the end
+    denied_warnings: Vec<usize>,        // the diagnostics that become errors once both passes are done
+    used_labels: HashSet<String>,       // labels something refers to
+    tests: Vec<TestBlock>,              // the .TEST blocks, run by the test runner
 }
 
 
@@ -41,7 +82,7 @@ impl Assembler
 {
     // new 
     // return a new assembler 
-    pub fn new(file_name: &str, output_file_name: &str) -> Result<Assembler, GeneralError>
+    pub fn new(file_name: &str, output_file_name: &str, options: AssemblerOptions) -> Result<Assembler, Diagnostic>
     {
         let  file_result = File::create(output_file_name);
         let  file;
@@ -49,8 +90,43 @@ impl Assembler
         match file_result 
         {
             Ok(f) => file = f,
-            Err(err) => return Err(Assembler::create_empty_error(err.to_string().as_str()))
+            Err(err) => return Err(Assembler::create_empty_error(codes::IO, err.to_string().as_str()))
+        }
+
+        let mut assembler = Assembler::without_output(file_name, options)?;
+        assembler.file_writer = Some(file);
+        Ok(assembler)
+    }
+
+    // without_output
+    // an assembler that only keeps the object
+    // code, the listing and other files still get written
+    pub fn without_output(file_name: &str, options: AssemblerOptions) -> Result<Assembler, Diagnostic>
+    {
+        // the module name defaults to the input file name without its extension
+        let mut object_code = ObjectCode::new();
+        object_code.module_name = match &options.module_name
+        {
+            Some(name) => name.to_string(),
+            None => Path::new(file_name).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default(),
+        };
+
+        // CHR-ROM for NES images comes from a binary file
+        if let Some(chr_file) = &options.chr_file
+        {
+            match std::fs::read(chr_file)
+            {
+                Ok(data) => object_code.ines.chr_data = data,
+                Err(err) => return Err(Assembler::create_empty_error(codes::IO, &format!("Can not read CHR file {}: {}", chr_file, err))),
+            }
         }
+
+        // instructions for a cpu the assembler doesn't know
+        let extra_opcodes = match &options.instruction_file
+        {
+            Some(instruction_file) => Opcode::load(instruction_file)?,
+            None => vec![],
+        };
         
     
      
@@ -59,27 +135,206 @@ impl Assembler
             read_file_name: file_name.to_string(),
             lexical_iterator: PeekWrapper::new(LexicalAnalyzer::new(file_name.to_string(), true)?.get_iterator(),3),
             symbol_table: HashMap::new(),
+            constants: HashSet::new(),
+            symbol_lines: HashMap::new(),
             current_byte: 0,
-            instruction_table: Instruction::get_map(),
-            file_writer: file,
+            cpu: options.cpu,
+            accumulator_16: false,
+            index_16: false,
+            instruction_table: Instruction::get_map_with(options.cpu, &extra_opcodes),
+            extra_opcodes,
+            file_writer: None,
+            object_code,
+            options,
+            source_lines: vec![],
+            diagnostics: vec![],
+            denied_warnings: vec![],
+            used_labels: HashSet::new(),
+            tests: vec![],
         })
     }
 
     // run 
-    // runs the assembler 
-    pub fn run(& mut self ) -> Result<(),GeneralError>
+    // runs the assembler, returns the warnings if
+    // it worked and everything it found if it didn't
+    pub fn run(& mut self ) -> Result<Vec<Diagnostic>,Vec<Diagnostic>>
     {
+        if let Err(err) = self.assemble()
+        {
+            self.report(err);
+        }
+        self.deny_warnings();
+
+        if self.has_errors()
+        {
+            return Err(self.diagnostics.clone());
+        }
+
+        Ok(self.diagnostics.clone())
+    }
 
+    // assemble
+    // does both passes and writes the outputs,
+    // the second pass only happens if the first
+    // one was clean since the addresses could be off
+    fn assemble(&mut self) -> Result<(),Diagnostic>
+    {
         self.first_pass()?;
+        if self.has_errors()
+        {
+            return Ok(());
+        }
+
         self.second_pass()?;
+        self.unused_label_warnings();
+        self.deny_warnings();
+        if self.has_errors()
+        {
+            return Ok(());
+        }
+
+        self.write_output()?;
+
+        if let Some(listing_file) = self.options.listing_file.clone()
+        {
+            self.write_listing(&listing_file)?;
+        }
+
+        for (format, symbol_file) in self.options.symbol_files.clone()
+        {
+            let text = symbol_file::symbol_file(format, &self.symbols(), &self.object_code);
+
+            if let Err(err) = std::fs::write(&symbol_file, text)
+            {
+                return Err(Assembler::create_empty_error(codes::IO, &format!("Problem writing the symbol file. details: {:?}", err)));
+            }
+        }
+
+        if self.options.map_file.is_some() || self.options.map_json_file.is_some()
+        {
+            self.write_memory_map()?;
+        }
+
+        if let Some(debug_file) = &self.options.debug_info_file
+        {
+            let text = debug_info::debug_info(&self.read_file_name, &self.source_lines, &self.symbols(), &self.object_code);
+
+            if let Err(err) = std::fs::write(debug_file, text)
+            {
+                return Err(Assembler::create_empty_error(codes::IO, &format!("Problem writing the debug info. details: {:?}", err)));
+            }
+        }
+
+        Ok(())
+    }
+
+    // write_memory_map
+    // writes the memory map report as text
+    // and or json, NES roms get the PPU and APU
+    // registers as I/O space if none was given
+    fn write_memory_map(&self) -> Result<(),Diagnostic>
+    {
+        let mut io_ranges = self.options.io_ranges.clone();
+        if io_ranges.is_empty() && matches!(self.options.output_format, OutputFormat::Ines { .. })
+        {
+            io_ranges.push((0x2000, 0x401f));
+        }
+
+        let map = MemoryMap::new(&self.object_code, &io_ranges);
+
+        let files = [(&self.options.map_file, map.text()), (&self.options.map_json_file, map.json())];
+        for (file, text) in files
+        {
+            if let Some(file) = file
+            {
+                if let Err(err) = std::fs::write(file, text)
+                {
+                    return Err(Assembler::create_empty_error(codes::IO, &format!("Problem writing the memory map. details: {:?}", err)));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // symbols
+    // the symbol table sorted by address
+    // then name, with the constants marked
+    pub fn symbols(&self) -> Vec<Symbol>
+    {
+        let mut symbols: Vec<Symbol> = self.symbol_table.iter().map(|(name, value)| Symbol {
+            name: name.to_string(),
+            value: *value,
+            constant: self.constants.contains(name),
+            line: self.symbol_lines.get(name).map(|(line, _, _)| *line).unwrap_or(0),
+        }).collect();
+
+        symbols.sort_by(|a, b| a.value.unwrap().cmp(&b.value.unwrap()).then(a.name.cmp(&b.name)));
+        symbols
+    }
+
+    // write_listing
+    // writes the listing file, each source line
+    // with its address and bytes then the symbol table
+    fn write_listing(&self, listing_file: &str) -> Result<(),Diagnostic>
+    {
+        let source = match std::fs::read_to_string(&self.read_file_name)
+        {
+            Ok(s) => s,
+            Err(err) => return Err(Assembler::create_empty_error(codes::IO, &format!("Can not read {} for the listing: {}", self.read_file_name, err))),
+        };
+
+        let text = listing::listing(&self.read_file_name, &source, &self.source_lines, &self.symbol_table);
+
+        if let Err(err) = std::fs::write(listing_file, text)
+        {
+            return Err(Assembler::create_empty_error(codes::IO, &format!("Problem writing the listing file. details: {:?}", err)));
+        }
+
+        Ok(())
+    }
+
+    // write_output
+    // renders the object code in the chosen
+    // output format and writes it to the output file
+    fn write_output(&mut self) -> Result<(),Diagnostic>
+    {
+        let file_writer = match &mut self.file_writer
+        {
+            Some(file_writer) => file_writer,
+            None => return Ok(()),
+        };
+
+        let bytes = self.options.output_format.render(&self.object_code)?;
+
+        if let Err(err) = file_writer.write_all(&bytes)
+        {
+            let error_string = format!("Problem writing to file. details: {:?}", err);
+            return Err(Assembler::create_empty_error(codes::IO, &error_string));
+        }
 
         Ok(())
     }
 
+    // emit
+    // puts bytes into the object code at the
+    // current byte and moves the current byte past them
+    fn emit(&mut self, bytes: &[u8])
+    {
+        if let Some(line) = self.source_lines.last_mut()
+        {
+            line.address.get_or_insert(self.current_byte);
+            line.bytes.extend_from_slice(bytes);
+        }
+
+        self.object_code.write(self.current_byte, bytes);
+        self.current_byte += bytes.len() as u32;
+    }
+
     // first_pass
     // finds all the labels on logical lines 
     // while checking syntax
-    fn first_pass(&mut self) ->Result<(),GeneralError>
+    fn first_pass(&mut self) ->Result<(),Diagnostic>
     {
 
         loop 
@@ -93,45 +348,40 @@ impl Assembler
                 Some(t) => token = t?,
             }
 
-            match token.token_type
-            {   
-                
-                TokenType::Directive => 
-                {
-                    Assembler::directive_parser(self, true)?;
-                }
-                TokenType::Label => 
-                {
-                    Assembler::label_parser(self, true)?;
-                },
-                TokenType::Instruction => 
-                {
-                  Assembler::instruction_parser( self, true)?;
-                },
-                TokenType::EOF =>
+            if token.token_type == TokenType::EOF
+            {
+                break;
+            }
+
+            if let Err(err) = Assembler::statement_parser(self, &token, true)
+            {
+                self.skip_line(token.file_line);
+
+                if !self.report(err)
                 {
                     break;
                 }
-                _ => {return Err(Assembler::create_error("Syntax Error", &token, vec![TokenType::Instruction, TokenType::Directive, TokenType::Label]))}
             }
-
         }
 
 
         Ok(())
     }
 
-    // first_pass
+    // second_pass
     // checks syntax while writting everything
     // to file
-    fn second_pass(&mut self) ->Result<(),GeneralError>
+    fn second_pass(&mut self) ->Result<(),Diagnostic>
     {
         
         // reset the lexical analyzer 
         // so we can do another pass
-        self.lexical_iterator = PeekWrapper::new(LexicalAnalyzer::new(self.read_file_name.to_string(), true).unwrap().get_iterator(),3);
+        self.lexical_iterator = PeekWrapper::new(LexicalAnalyzer::new(self.read_file_name.to_string(), true)?.get_iterator(),3);
         
         self.current_byte = 0;
+        self.set_cpu(self.options.cpu);
+        self.accumulator_16 = false;
+        self.index_16 = false;
 
         loop 
         {   
@@ -144,49 +394,187 @@ impl Assembler
                 Some(t) => token = t?,
             }
 
-            match token.token_type
-            {   
-                TokenType::Directive =>
-                {
-                    Assembler::directive_parser(self, false)?;
-                }
-                TokenType::Label => 
-                {
-                    Assembler::label_parser(self, false)?;
-                },
-                TokenType::Instruction => 
-                {
-                  Assembler::instruction_parser( self, false)?;
-                },
-                TokenType::EOF =>
+            if token.token_type == TokenType::EOF
+            {
+                break;
+            }
+
+            // a label and an instruction can share a line
+            // so only start a new listing line when the line changes
+            if self.source_lines.last().map(|l| l.file_line) != Some(token.file_line)
+            {
+                self.source_lines.push(SourceLine { file_line: token.file_line, address: None, bytes: vec![] });
+            }
+
+            if let Err(err) = Assembler::statement_parser(self, &token, false)
+            {
+                self.skip_line(token.file_line);
+
+                if !self.report(err)
                 {
                     break;
                 }
-                _ => {return Err(Assembler::create_error("Syntax Error", &token, vec![TokenType::Instruction, TokenType::Directive, TokenType::Label]))}
             }
-
         }
 
 
         Ok(())
     }
 
+    // statement_parser
+    // parses whatever starts with the given token,
+    // a directive, a label or an instruction
+    fn statement_parser(assembler: &mut Assembler, token: &Token, first_pass: bool) -> Result<(),Diagnostic>
+    {
+        match token.token_type
+        {   
+            TokenType::Directive => Assembler::directive_parser(assembler, first_pass),
+            // mnemonics the lexer doesn't know, like the 65C02 ones,
+            // come in as labels so they can still be labels on other cpus
+            TokenType::Label if assembler.instruction_table.contains_key(&token.value.to_lowercase()) => Assembler::instruction_parser(assembler, first_pass),
+            TokenType::Label => Assembler::label_parser(assembler, first_pass),
+            TokenType::Instruction => Assembler::instruction_parser(assembler, first_pass),
+            _ => Err(Assembler::create_error(codes::SYNTAX, "Syntax error", token, vec![TokenType::Instruction, TokenType::Directive, TokenType::Label]))
+        }
+    }
+
+    // set_cpu
+    // switches to another cpu and
+    // the instructions it has
+    fn set_cpu(&mut self, cpu: Cpu)
+    {
+        if self.cpu != cpu
+        {
+            self.cpu = cpu;
+            self.instruction_table = Instruction::get_map_with(cpu, &self.extra_opcodes);
+        }
+    }
+
+    // has_errors
+    // true if anything reported was an error
+    fn has_errors(&self) -> bool
+    {
+        self.diagnostics.iter().any(|d| d.is_error())
+    }
+
+    // warn
+    // reports a warning if it is turned on,
+    // as an error if it was made into one
+    fn warn(&mut self, warning: Diagnostic)
+    {
+        let level = match Warning::from_code(warning.code)
+        {
+            Some(w) => self.options.warning_level(w),
+            None => WarningLevel::Warn,
+        };
+
+        match level
+        {
+            WarningLevel::Allow => return,
+            WarningLevel::Deny => self.denied_warnings.push(self.diagnostics.len()),
+            WarningLevel::Warn => {}
+        }
+
+        self.report(warning);
+    }
+
+    // deny_warnings
+    // makes the warnings that were turned into errors
+    // errors, only after the passes so they don't stop
+    // the second pass from reporting its own errors
+    fn deny_warnings(&mut self)
+    {
+        for index in self.denied_warnings.drain(..)
+        {
+            self.diagnostics[index].severity = Severity::Error;
+        }
+    }
+
+    // unused_label_warnings
+    // warns about every label that
+    // nothing referred to
+    fn unused_label_warnings(&mut self)
+    {
+        let unused: Vec<Symbol> = self.symbols().into_iter().filter(|s| !self.used_labels.contains(&s.name)).collect();
+
+        for symbol in unused
+        {
+            let mut warning = Diagnostic::new(codes::UNUSED_LABEL, &format!("Label {} is never used", symbol.name), "Assembler");
+            warning.severity = Severity::Warning;
+            warning.line = symbol.line;
+            self.warn(warning);
+        }
+    }
+
+    // report
+    // keeps an error to show at the end,
+    // returns false once there are so many
+    // that it isn't worth going on
+    fn report(&mut self, mut err: Diagnostic) -> bool
+    {
+        // only the assembler knows which file it is reading
+        if err.line != 0 && err.file.is_none()
+        {
+            err.file = Some(self.read_file_name.to_string());
+        }
+        for note in &mut err.notes
+        {
+            if note.line != 0 && note.file.is_none()
+            {
+                note.file = Some(self.read_file_name.to_string());
+            }
+        }
+
+        self.diagnostics.push(err);
+
+        let errors = self.diagnostics.iter().filter(|d| d.is_error()).count();
+        self.options.max_errors == 0 || errors < self.options.max_errors
+    }
+
+    // skip_line
+    // throws away what is left of the line an error
+    // happened on so the next line can be parsed,
+    // the parser may have already eaten its EOL
+    fn skip_line(&mut self, file_line: u32)
+    {
+        loop
+        {
+            match self.lexical_iterator.peek(0)
+            {
+                Some(Ok(t)) if t.token_type == TokenType::EOF => break,
+                Some(Ok(t)) if t.token_type == TokenType::EOL =>
+                {
+                    self.lexical_iterator.next();
+                    break;
+                }
+                Some(Ok(t)) if t.file_line != file_line => break,
+                Some(Ok(_)) => { self.lexical_iterator.next(); },
+                _ => break,
+            }
+        }
+    }
+
     // directive_parser 
     // parses directives if the directive
     // given isn't an implemented directive it throws an error
-    fn directive_parser(assembler: &mut Assembler, first_pass: bool)-> Result<(),GeneralError>
+    fn directive_parser(assembler: &mut Assembler, first_pass: bool)-> Result<(),Diagnostic>
     {
         let token = assembler.lexical_iterator.peek(0).unwrap()?;
 
         let mut _parsed_something = false;
         _parsed_something = _parsed_something || Assembler::byte_directive_parser(assembler, first_pass)?;
         _parsed_something = _parsed_something || Assembler::org_directive_parser(assembler)?;
+        _parsed_something = _parsed_something || Assembler::start_directive_parser(assembler, first_pass)?;
+        _parsed_something = _parsed_something || Assembler::ines_directive_parser(assembler, first_pass)?;
+        _parsed_something = _parsed_something || Assembler::cpu_directive_parser(assembler)?;
+        _parsed_something = _parsed_something || Assembler::register_width_directive_parser(assembler)?;
+        _parsed_something = _parsed_something || Assembler::test_directive_parser(assembler, first_pass)?;
 
 
         // it didn't parse anything 
         if !_parsed_something 
         {
-            return Err(Assembler::create_error("Syntax Error, directive not implemented", &token, vec![TokenType::Directive]));
+            return Err(Assembler::create_error(codes::UNKNOWN_DIRECTIVE, "Directive not implemented", &token, vec![TokenType::Directive]));
         }
 
         Ok(())
@@ -195,7 +583,7 @@ impl Assembler
 
     // label_parser_first_pass
     // adds a label to the symbol table
-    fn label_parser(assembler: &mut Assembler, first_pass:bool) -> Result<(),GeneralError>
+    fn label_parser(assembler: &mut Assembler, first_pass:bool) -> Result<(),Diagnostic>
     {
 
         // basically just consumes the tokens
@@ -210,7 +598,7 @@ impl Assembler
             // unwrap it 
             if let None = token_option
             {
-                return Err(Assembler::create_empty_error("Something bad happened in the label parser on the second pass"));
+                return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the label parser on the second pass"));
             }
             else 
             {
@@ -226,6 +614,11 @@ impl Assembler
             // this is a normal label that points to a place in code or memory
             else
             {
+                if let Some(line) = assembler.source_lines.last_mut()
+                {
+                    line.address.get_or_insert(assembler.current_byte);
+                }
+
                 Assembler::consume_if_available(TokenType::Collon, &mut assembler.lexical_iterator)?;
                 Assembler::consume_if_available(TokenType::EOL, &mut assembler.lexical_iterator)?;
             }
@@ -244,16 +637,35 @@ impl Assembler
         // unwrap it 
         if let None = token_option
         {
-            return Err(Assembler::create_empty_error("Something bad happened in the label parser on the first pass"));
+            return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the label parser on the first pass"));
         }
         else 
         {
             next_token = token_option.unwrap()?;
         }
 
-        let mut label_num_value:InsertableNum = InsertableNum::Byte(0); // just initializing it to 0 
-        
-        if next_token.token_type == TokenType::EQUALS
+        // an instruction of a cpu that isn't on gets here as a label,
+        // it is only a label with a : or = after it, PHX on a line of
+        // its own is the instruction and not a label that emits nothing
+        if !matches!(next_token.token_type, TokenType::Collon | TokenType::EQUALS) && Instruction::is_other_mnemonic(&label_token.value)
+        {
+            // with nothing after it the cpu wanted is one where
+            // it takes no operand, SAX is a store on the 6502X
+            // but swaps A and X on the HuC6280
+            let name = label_token.value.to_lowercase();
+            let no_operand = matches!(next_token.token_type, TokenType::EOL | TokenType::Instruction | TokenType::Directive);
+            let has = |cpu: &Cpu, implied_only: bool| Instruction::opcodes(*cpu, &[]).iter()
+                .any(|o| o.mnemonic == name && (!implied_only || matches!(o.mode, AddressingMode::Implied | AddressingMode::Accumulator)));
+
+            let cpu = Cpu::all().into_iter().find(|c| has(c, no_operand)).or_else(|| Cpu::all().into_iter().find(|c| has(c, false)));
+            if let Some(cpu) = cpu
+            {
+                let message = format!("{} is an instruction on the {}, turn it on with .CPU {} or --cpu {}", label_token.value.to_uppercase(), cpu, cpu, cpu.to_string().to_lowercase());
+                return Err(Assembler::create_error(codes::UNKNOWN_INSTRUCTION, &message, &label_token, vec![]));
+            }
+        }
+
+        let label_num_value = if next_token.token_type == TokenType::EQUALS
         {
             // consume the equals
             Assembler::consume_if_available(TokenType::EQUALS, &mut assembler.lexical_iterator)?;
@@ -263,7 +675,8 @@ impl Assembler
 
             Assembler::check_label_expression_syntax(&mut token_stack)?;
 
-             label_num_value = Assembler::expression(assembler, & mut token_stack)?;
+            assembler.constants.insert(label_token.value.to_string());
+            Assembler::expression(assembler, & mut token_stack)?
         }
         else 
         {
@@ -271,9 +684,9 @@ impl Assembler
             Assembler::consume_if_available(TokenType::Collon, &mut assembler.lexical_iterator)?;
             Assembler::consume_if_available(TokenType::EOL, &mut assembler.lexical_iterator)?;
 
-            // insert the num
-            label_num_value = InsertableNum::TwoByte(assembler.current_byte as u16);
-        }
+            // insert the num, past the first bank it takes 3 bytes
+            InsertableNum::sized(assembler.current_byte, 2)
+        };
 
 
         let insert_option = assembler.symbol_table.insert(label_token.value.to_string(), label_num_value);
@@ -283,7 +696,22 @@ impl Assembler
         // because that means the line exists 
         if let Some(_) = insert_option
         {
-            return Err(Assembler::create_error("Label is already defined", &label_token, vec![]));
+            let (line, column, length) = *assembler.symbol_lines.get(&label_token.value).unwrap_or(&(0, 0, 0));
+            let mut note = Diagnostic::note("first defined here", line);
+            if length > 0
+            {
+                note.columns = Some((column, column + length));
+            }
+            return Err(Assembler::create_error(codes::DUPLICATE_LABEL, "Label is already defined", &label_token, vec![])
+                .with_note(note));
+        }
+
+        assembler.symbol_lines.insert(label_token.value.to_string(), (label_token.file_line, label_token.column, label_token.length));
+
+        if Instruction::is_other_mnemonic(&label_token.value)
+        {
+            let message = format!("Label {} has the name of an instruction on other 6502s", label_token.value);
+            assembler.warn(Diagnostic::warning(Warning::LabelShadowsMnemonic, &message, &label_token));
         }
 
         Ok(())
@@ -295,10 +723,10 @@ impl Assembler
     // checks the syntax of a label
     // that points to a variable 
     // makes sure that the expression is legit
-    fn check_label_expression_syntax(token_vec:&Vec<Token>)-> Result<(), GeneralError>
+    fn check_label_expression_syntax(token_vec:&Vec<Token>)-> Result<(), Diagnostic>
     {
         let  operator:Vec<TokenType> = vec![TokenType::PLUS, TokenType::MINUS, TokenType::DIVIDE, TokenType::TIMES];
-        let  operand:Vec<TokenType> = vec![TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Label];
+        let  operand:Vec<TokenType> = vec![TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Num3Bytes, TokenType::Label];
         let  end:Vec<TokenType> = vec![TokenType::EOL];
 
 
@@ -308,7 +736,7 @@ impl Assembler
         // start => "(" || operand
         if !(operand.contains(&token_vec[0].token_type) || token_vec[0].token_type == TokenType::LeftParenth)
         {
-            return Err(Assembler::create_error("Syntax error", &token_vec[0], [&[TokenType::LeftParenth], operand.as_slice()].concat()))
+            return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[0], [&[TokenType::LeftParenth], operand.as_slice()].concat()))
         }
 
         for (i,t) in token_vec.iter().enumerate()
@@ -319,19 +747,19 @@ impl Assembler
 
                 if !(operand.contains(&token_vec[i+1].token_type) || token_vec[i+1].token_type == TokenType::LeftParenth)
                 {
-                    return Err(Assembler::create_error("Syntax error", &token_vec[i+1], operand))
+                    return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[i+1], operand))
                 }
                 left_parenth_count = left_parenth_count + 1;
             } 
             // operand => ")" ||  operator || End
             else if operand.contains(&t.token_type) && !(token_vec[i+1].token_type == TokenType::RightParenth || operator.contains(&token_vec[i+1].token_type) || end.contains(&token_vec[i+1].token_type))
             {
-                return Err(Assembler::create_error("Syntax error", &token_vec[i+1], [&[TokenType::RightParenth], operator.as_slice()].concat()));
+                return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[i+1], [&[TokenType::RightParenth], operator.as_slice()].concat()));
             }
             // operator => operand || "("
             else if operator.contains(&t.token_type) && !(operand.contains(&token_vec[i+1].token_type) || token_vec[i+1].token_type != TokenType::RightParenth)
             {
-                return Err(Assembler::create_error("Syntax error", &token_vec[i+1], [&[TokenType::LeftParenth], operand.as_slice()].concat()));
+                return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[i+1], [&[TokenType::LeftParenth], operand.as_slice()].concat()));
             }
             //  ")" => operator || end || ")"
             else if t.token_type == TokenType::RightParenth 
@@ -339,12 +767,12 @@ impl Assembler
 
                 if !(operator.contains(&token_vec[i+1].token_type) || end.contains(&token_vec[i+1].token_type) || token_vec[i+1].token_type == TokenType::RightParenth)
                 {
-                    return Err(Assembler::create_error("Syntax error", &token_vec[i+1], [end.as_slice(), operator.as_slice()].concat()));
+                    return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[i+1], [end.as_slice(), operator.as_slice()].concat()));
                 }
                 
                 if left_parenth_count == 0
                 {
-                    return Err(Assembler::create_error("Syntax error, unmatched right parenth", &t, vec![TokenType::LeftParenth]));
+                    return Err(Assembler::create_error(codes::UNMATCHED_PARENTH, "Unmatched right parenth", &t, vec![TokenType::LeftParenth]));
                 }
                 else
                 {
@@ -359,12 +787,12 @@ impl Assembler
 
                 if !end.contains(&t.token_type)
                 {
-                    return Err(Assembler::create_error("Syntax error", &t, [end.as_slice()].concat()));
+                    return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &t, [end.as_slice()].concat()));
                 }
 
                 if left_parenth_count > 0
                 {
-                    return Err(Assembler::create_error("Syntax error, unmatched left parenth", &t, vec![TokenType::RightParenth]));
+                    return Err(Assembler::create_error(codes::UNMATCHED_PARENTH, "Unmatched left parenth", &t, vec![TokenType::RightParenth]));
                 }
             }
 
@@ -379,7 +807,7 @@ impl Assembler
     // from the lexical analyzer and writes it to file
     // if first_pass=false, replaces labels with their value on the symbol table
     // TODO: clean up this code a little
-    fn instruction_parser(assembler: &mut Assembler, first_pass: bool)-> Result<(),GeneralError>
+    fn instruction_parser(assembler: &mut Assembler, first_pass: bool)-> Result<(),Diagnostic>
     {
         // get all the tokens until eol 
         let mut gotten_tokens:Vec<Token> = vec![];
@@ -387,6 +815,13 @@ impl Assembler
         let token_instruction = &Assembler::unwrap_token_option(assembler.lexical_iterator.next(), &mut assembler.lexical_iterator)?;
         Assembler::get_until_eol(assembler, &mut gotten_tokens)?;
 
+        // S is only the stack pointer in the 65816's
+        // sr,S operands, anywhere else it is a label
+        if assembler.cpu.is_65816()
+        {
+            Assembler::stack_register(&mut gotten_tokens);
+        }
+
         // get the expected grammars for this 
         let instruction_option = assembler.instruction_table.get(&token_instruction.value.to_lowercase());
 
@@ -394,26 +829,41 @@ impl Assembler
 
         if let None = instruction_option 
         {
-            return Err(Assembler::create_error("Instruction not implemented", &token_instruction, vec![TokenType::Instruction]));
+            return Err(Assembler::create_error(codes::UNKNOWN_INSTRUCTION, "Instruction not implemented", &token_instruction, vec![TokenType::Instruction]));
         }
         else 
         {
-            instruction = instruction_option.unwrap();
+            instruction = instruction_option.unwrap().clone();
         }
 
+        // a 65816 register set to 16 bits takes a 2 byte immediate
+        let wide = (assembler.accumulator_16 && instruction.uses_accumulator_width()) || (assembler.index_16 && instruction.uses_index_width());
+        let instruction = if assembler.cpu.is_65816() && wide { instruction.with_wide_immediate() } else { instruction };
+
         let mut best_match = &instruction.opcode_grammer[0];
         let mut best_match_count:usize = 0;
-        let mut expected_index:usize=0;
+        let mut expected:Vec<TokenType> = vec![];  // what the grammars that got furthest wanted next
         let mut matched = false;
+        let mut first_error: Option<Diagnostic> = None;  // a grammar that couldn't make sense of it, only matters if none could
 
         for grammar in &instruction.opcode_grammer
         {
-            let did_it_match = Assembler::check_instruction_syntax(assembler,& mut gotten_tokens, &grammar.1)?;
+            let did_it_match = match Assembler::check_instruction_syntax(assembler,& mut gotten_tokens, &grammar.1)
+            {
+                Ok(m) => m,
+                Err(err) =>
+                {
+                    first_error.get_or_insert(err);
+                    continue;
+                }
+            };
 
             // if its the first pass and it matched
-            // just return
+            // count its bytes so labels after it land in the right place
             if did_it_match.0 && first_pass
             {
+                // branches store their target in one byte
+                assembler.current_byte += Assembler::grammar_size(&grammar.1) - if instruction.is_branch() { 1 } else { 0 };
                 return  Ok(())
             }
             // if its not first pass and matched
@@ -426,12 +876,30 @@ impl Assembler
                 break;
             }
             // didn't match so see if this is the best
-            // matching grammar this far
-            else if did_it_match.1 > best_match_count
+            // matching grammar this far, grammars that got
+            // just as far add what they expected too
+            else
             {
-                best_match_count = did_it_match.1;
-                best_match = grammar;
-                expected_index = did_it_match.2;
+                if did_it_match.1 > best_match_count || expected.is_empty()
+                {
+                    best_match_count = did_it_match.1;
+                    expected.clear();
+                }
+
+                if did_it_match.1 == best_match_count
+                {
+                    // a comma is only half the story, ,X and ,Y are what is wanted
+                    let mut wanted = vec![grammar.1[did_it_match.2]];
+                    if wanted[0] == TokenType::Comma && did_it_match.2+1 < grammar.1.len()
+                    {
+                        wanted.push(grammar.1[did_it_match.2+1]);
+                    }
+
+                    if !expected.windows(wanted.len()).any(|w| w == wanted.as_slice())
+                    {
+                        expected.extend(wanted);
+                    }
+                }
             }
 
         }
@@ -440,14 +908,53 @@ impl Assembler
         // didn't match anthing
         if !matched 
         {
-            return Err(Assembler::create_error("Syntax error", &gotten_tokens[best_match_count], vec![best_match.1[expected_index]]));
+            // only ask for numbers as big as some mode of it takes
+            let widest = instruction.opcode_grammer.iter().flat_map(|g| g.1.iter()).map(|t| Assembler::number_size(*t)).max().unwrap_or(0);
+            let fits = |t: &TokenType| Assembler::number_size(*t) <= widest;
+            expected.retain(fits);
+            if let Some(err) = &mut first_error
+            {
+                err.expected.retain(fits);
+            }
+
+            // it might be a mode another cpu has, like NOP #$00 on the 6502X
+            let mut stack_tokens = gotten_tokens.clone();
+            Assembler::stack_register(&mut stack_tokens);
+
+            let other_cpu = Cpu::all().into_iter().filter(|c| *c != assembler.cpu).find(|c| {
+                let tokens = if c.is_65816() { &mut stack_tokens } else { &mut gotten_tokens };
+                match Instruction::get_map(*c).get(&instruction.string_code)
+                {
+                    Some(other) => other.opcode_grammer.iter().any(|g| matches!(Assembler::check_instruction_syntax(assembler, tokens, &g.1), Ok((true, _, _)))),
+                    None => false,
+                }
+            });
+
+            if let Some(cpu) = other_cpu
+            {
+                let message = format!("{} can only be used like this on the {}, turn it on with .CPU {} or --cpu {}", token_instruction.value.to_uppercase(), cpu, cpu, cpu.to_string().to_lowercase());
+                return Err(Assembler::create_error(codes::UNKNOWN_INSTRUCTION, &message, &gotten_tokens[best_match_count], expected));
+            }
+
+            if let Some(err) = first_error
+            {
+                return Err(err);
+            }
+
+            return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &gotten_tokens[best_match_count], expected));
+        }
+
+        if instruction.string_code == "sed" && !assembler.cpu.has_decimal_mode()
+        {
+            let message = format!("SED does nothing useful on the {}, it has no decimal mode", assembler.cpu);
+            assembler.warn(Diagnostic::warning(Warning::NoDecimalMode, &message, token_instruction));
         }
 
         // write the instruction to file 
-        assembler.file_writer.write(&[best_match.0]).unwrap();
+        assembler.emit(&[best_match.0]);
 
 
-        let expression_index =  best_match.1.iter().position(|&r| (r == TokenType::Num1Bytes || r == TokenType::Num2Bytes));
+        let expression_index =  best_match.1.iter().position(|&r| Assembler::number_size(r) > 0);
         
         // there is an expression so parse it 
         if let Some(i) = expression_index
@@ -455,50 +962,197 @@ impl Assembler
   
             let expression_type = best_match.1[i];
             let expression_stack = &gotten_tokens[i..gotten_tokens.len()-(best_match.1.len()-i-1)];
+
+            // BBR and BBS, MVN and MVP, TST and the block transfers
+            // have more than one number with commas between them
+            let slots: Vec<TokenType> = best_match.1.iter().copied().filter(|t| Assembler::number_size(*t) > 0).collect();
+            if slots.len() > 1
+            {
+                let operands: Vec<&[Token]> = gotten_tokens[..gotten_tokens.len()-1]
+                    .split(|t| t.token_type == TokenType::Comma)
+                    .map(|g| if g[0].token_type == TokenType::Hash { &g[1..] } else { g })
+                    .collect();
+
+                let mut values = vec![];
+                for operand in &operands[..slots.len()]
+                {
+                    values.push(Assembler::expression(assembler, operand)?);
+                }
+
+                // the zero page byte comes before the branch offset
+                if instruction.is_bit_branch()
+                {
+                    assembler.emit(&[values[0].unwrap_byte()]);
+                    let offset = Assembler::branch_offset(assembler, values[1].unwrap(), &operands[1][0])?;
+                    assembler.emit(&[offset as u8]);
+                    return Ok(());
+                }
+
+                // MVN and MVP are written source then destination
+                // but the destination bank goes first
+                if instruction.is_block_move()
+                {
+                    values.reverse();
+                }
+
+                for (value, slot) in values.iter().zip(slots)
+                {
+                    assembler.emit(&InsertableNum::sized(value.unwrap(), Assembler::number_size(slot)).to_bytes());
+                }
+                return Ok(());
+            }
+
             let num = Assembler::expression(assembler, expression_stack)?;
 
-            if expression_type == TokenType::Num1Bytes
+            if instruction.is_branch()
+            {
+                let offset = Assembler::branch_offset(assembler, num.unwrap(), &expression_stack[0])?;
+                assembler.emit(&[offset as u8]);
+            }
+            else if instruction.is_long_branch()
             {
-                assembler.file_writer.write(&[num.unwrap_byte()]).unwrap();
+                let offset = Assembler::long_branch_offset(assembler, num.unwrap(), &expression_stack[0])?;
+                assembler.emit(&(offset as u16).to_le_bytes());
             }
             else
             {
-                if !num.is_two_bytes()
+                let size = Assembler::number_size(expression_type);
+
+                // immediates grow with the register and aren't worth a warning
+                if num.size() < size && best_match.1[0] != TokenType::Hash
                 {
-                    println!("WARNING: \n {}: Just an fyi, upcasting 1 byte to 2 bytes for best matching instruction.",gotten_tokens[0].file_line);
+                    let message = format!("Using {} byte value ${:0width$X} as {} bytes, {} has no {} byte mode that fits", num.size(), num.unwrap(), size, instruction.string_code.to_uppercase(), num.size(), width = 2 * num.size() as usize);
+                    assembler.warn(Diagnostic::warning(Warning::ImplicitUpcast, &message, &expression_stack[0]));
                 }
-                let num16 = num.unwrap_twobyte();
-                assembler.file_writer.write(&[num16 as u8, (num16 >> 8) as u8]).unwrap();
+                assembler.emit(&InsertableNum::sized(num.unwrap(), size).to_bytes());
             }
         }
 
         Ok(())
     }
 
-    // get_until_eol
-    // get tokens from assembler
-    // util eol and put them in a vector
-    fn get_until_eol(assembler: &mut Assembler, vector:&mut Vec<Token>) -> Result<(),GeneralError>
+    // branch_offset
+    // the offset a branch at the current byte
+    // needs to land on target, errors if it can't
+    // reach and warns if it only just can
+    fn branch_offset(assembler: &mut Assembler, target: u32, token: &Token) -> Result<i8,Diagnostic>
     {
-        let mut gotten_eol = false;
+        // relative to the byte after the branch
+        let offset = Assembler::relative_offset(assembler.current_byte + 1, target);
 
-        while !gotten_eol 
+        if !(-128..=127).contains(&offset)
         {
-            let opt = assembler.lexical_iterator.next();
+            let message = format!("Branch target is {} bytes away, a branch can only reach -128 to 127", offset);
+            return Err(Assembler::create_error(codes::VALUE_OUT_OF_RANGE, &message, token, vec![]));
+        }
 
-            if let None = opt 
-            {
-                return Err(Assembler::create_empty_error("Something went wrong in get_until_eof function. This is a developer error"));
-            }
-            else 
-            {
-                let tok = opt.unwrap()?;
-                
+        if !(-128 + BRANCH_MARGIN..=127 - BRANCH_MARGIN).contains(&offset)
+        {
+            let message = format!("Branch target is {} bytes away, close to the -128 to 127 limit", offset);
+            assembler.warn(Diagnostic::warning(Warning::BranchRange, &message, token));
+        }
+
+        Ok(offset as i8)
+    }
+
+    // long_branch_offset
+    // the offset BRL or PER at the current
+    // byte needs to land on target
+    fn long_branch_offset(assembler: &mut Assembler, target: u32, token: &Token) -> Result<i16,Diagnostic>
+    {
+        // relative to the byte after the 2 byte offset
+        let offset = Assembler::relative_offset(assembler.current_byte + 2, target);
+
+        if !(-32768..=32767).contains(&offset)
+        {
+            let message = format!("Branch target is {} bytes away, a long branch can only reach -32768 to 32767", offset);
+            return Err(Assembler::create_error(codes::VALUE_OUT_OF_RANGE, &message, token, vec![]));
+        }
+
+        Ok(offset as i16)
+    }
+
+    // relative_offset
+    // how far target is from next, the program counter
+    // wraps around inside the bank the branch is in so a
+    // target in that bank is reached the short way round
+    fn relative_offset(next: u32, target: u32) -> i32
+    {
+        if (next - 1) >> 16 == target >> 16
+        {
+            (target as u16).wrapping_sub(next as u16) as i16 as i32
+        }
+        else
+        {
+            target as i32 - next as i32
+        }
+    }
+
+    // grammar_size
+    // how many bytes an instruction with
+    // the given grammar takes up, opcode included
+    fn grammar_size(grammar: &[TokenType]) -> u32
+    {
+        1 + grammar.iter().map(|t| Assembler::number_size(*t)).sum::<u32>()
+    }
+
+    // number_size
+    // how many bytes a number token
+    // stands for, 0 if it isn't one
+    fn number_size(token_type: TokenType) -> u32
+    {
+        match token_type
+        {
+            TokenType::Num1Bytes => 1,
+            TokenType::Num2Bytes => 2,
+            TokenType::Num3Bytes => 3,
+            _ => 0,
+        }
+    }
+
+    // stack_register
+    // turns an S label after a comma into the
+    // stack pointer register for the sr,S modes
+    fn stack_register(tokens: &mut [Token])
+    {
+        for i in 1..tokens.len()
+        {
+            if tokens[i-1].token_type == TokenType::Comma && tokens[i].token_type == TokenType::Label && tokens[i].value.eq_ignore_ascii_case("s")
+            {
+                tokens[i].token_type = TokenType::RegS;
+            }
+        }
+    }
+
+    // get_until_eol
+    // get tokens from assembler
+    // util eol and put them in a vector
+    fn get_until_eol(assembler: &mut Assembler, vector:&mut Vec<Token>) -> Result<(),Diagnostic>
+    {
+        let mut gotten_eol = false;
+
+        while !gotten_eol 
+        {
+            let opt = assembler.lexical_iterator.next();
+
+            if let None = opt 
+            {
+                return Err(Assembler::create_empty_error(codes::INTERNAL, "Something went wrong in get_until_eof function. This is a developer error"));
+            }
+            else 
+            {
+                let tok = opt.unwrap()?;
+                
                 if tok.token_type == TokenType::EOL
                 {
                     gotten_eol = true;
                 }
 
+                if tok.token_type == TokenType::Label
+                {
+                    assembler.used_labels.insert(tok.value.to_string());
+                }
+
                 vector.push(tok);
             }
             
@@ -512,15 +1166,16 @@ impl Assembler
     // converts a label expression into a single 
     // expression unless we don't wanna check variable existence in
     // that case it just returns a 
-    fn expression(assembler: &Assembler, expression_stack: &[Token]) -> Result<InsertableNum,GeneralError>
+    fn expression(assembler: &mut Assembler, expression_stack: &[Token]) -> Result<InsertableNum,Diagnostic>
     {
+        let mut warnings:Vec<Diagnostic> = vec![];
 
 
         let mut operand_stack:Vec<InsertableNum> = vec![];
         let mut operator_stack:Vec<Token> = vec![];
 
         let operators = vec![TokenType::LeftParenth, TokenType::PLUS, TokenType::MINUS, TokenType::TIMES, TokenType::DIVIDE];
-        let operands = vec![TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Label];
+        let operands = vec![TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Num3Bytes, TokenType::Label];
 
         for i in expression_stack
         {
@@ -541,15 +1196,20 @@ impl Assembler
                 }
                 else if i.token_type == TokenType::Num2Bytes 
                 {
+                    warnings.extend(Assembler::literal_truncated(i));
                     num = InsertableNum::TwoByte(Assembler::two_byte_num_string_to_int(i.value.clone()));
                 }
+                else if i.token_type == TokenType::Num3Bytes
+                {
+                    num = InsertableNum::ThreeByte(Assembler::three_byte_num_string_to_int(i.value.clone()));
+                }
                 else
                 {
                     let option = assembler.symbol_table.get(&i.value);
 
                     if let None = option
                     {
-                            return Err(Assembler::create_error("Syntax error, label doesn't exist", &i, vec![]))
+                            return Err(Assembler::create_error(codes::UNDEFINED_LABEL, "Label not defined", &i, vec![]))
                     }
                     else
                     {
@@ -562,13 +1222,18 @@ impl Assembler
             
             else if i.token_type == TokenType::RightParenth
             {
-                stack_math(&mut operand_stack, &mut operator_stack)?;
+                stack_math(&mut operand_stack, &mut operator_stack, &mut warnings)?;
             }
         }
 
 
 
-        stack_math(&mut operand_stack, &mut operator_stack)?;
+        stack_math(&mut operand_stack, &mut operator_stack, &mut warnings)?;
+
+        for warning in warnings
+        {
+            assembler.warn(warning);
+        }
 
 
         Ok(operand_stack.pop().unwrap())
@@ -578,11 +1243,11 @@ impl Assembler
     // this does two things, checks if the token_vec
     // matches the given token_grammar, and it returns 
     // how far it matched if it didn't
-    fn check_instruction_syntax(assembler:&Assembler,token_vec:& mut Vec<Token>, token_grammar:&Vec<TokenType>)-> Result<(bool,usize,usize),GeneralError>
+    fn check_instruction_syntax(assembler:&Assembler,token_vec:& mut Vec<Token>, token_grammar:&Vec<TokenType>)-> Result<(bool,usize,usize),Diagnostic>
     {
         let  operator:Vec<TokenType> = vec![TokenType::PLUS, TokenType::MINUS, TokenType::DIVIDE, TokenType::TIMES];
-        let  operand:Vec<TokenType> = vec![TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Label];
-        let  end:Vec<TokenType> = vec![TokenType::EOL, TokenType::Comma,];
+        let  operand:Vec<TokenType> = vec![TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Num3Bytes, TokenType::Label];
+        let  end:Vec<TokenType> = vec![TokenType::EOL, TokenType::Comma, TokenType::RightBracket];
 
 
 
@@ -609,7 +1274,7 @@ impl Assembler
         {
 
             // expression
-            if (token_grammar[current_token_grammar_index] == TokenType::Num1Bytes || token_grammar[current_token_grammar_index] == TokenType::Num2Bytes) || in_expression
+            if Assembler::number_size(token_grammar[current_token_grammar_index]) > 0 || in_expression
             {
 
 
@@ -622,7 +1287,7 @@ impl Assembler
 
                     if !(operand.contains(&token_vec[i+1].token_type) || token_vec[i+1].token_type == TokenType::LeftParenth)
                     {
-                        return Err(Assembler::create_error("Syntax error", &token_vec[i+1], operand));
+                        return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[i+1], operand));
                     }
                     left_parenth_count = left_parenth_count + 1;
                 }
@@ -637,26 +1302,26 @@ impl Assembler
 
                         if let None = t 
                         {
-                            return Err(Assembler::create_error("Syntax error, label not defined", &token_vec[i], vec![]));
+                            return Err(Assembler::create_error(codes::UNDEFINED_LABEL, "Label not defined", &token_vec[i], vec![]));
                         }
                         else 
                         {
                             let num = t.unwrap();
                         
-                            if num.is_two_bytes() && token_grammar[current_token_grammar_index] == TokenType::Num1Bytes
+                            if num.size() > Assembler::number_size(token_grammar[current_token_grammar_index])
                             {
                                  return Ok((false,i,current_token_grammar_index));
                             }
                         }
                     }
-                    else if token.token_type == TokenType::Num2Bytes && token_grammar[current_token_grammar_index] == TokenType::Num1Bytes
+                    else if Assembler::number_size(token.token_type) > Assembler::number_size(token_grammar[current_token_grammar_index])
                     {
                         return Ok((false,i,current_token_grammar_index));
                     }
 
                     if !(operator.contains(&token_vec[i+1].token_type) || token_vec[i+1].token_type == TokenType::RightParenth || end.contains(&token_vec[i+1].token_type))
                     {
-                        return Err(Assembler::create_error("Syntax error", &token_vec[i+1], [operator.as_slice(), &[TokenType::RightParenth], end.as_slice()].concat()));
+                        return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[i+1], [operator.as_slice(), &[TokenType::RightParenth], end.as_slice()].concat()));
                     }
 
                     if  i+1 != token_vec.len() && token_vec[i+1].token_type == TokenType::RightParenth && left_parenth_count == 0 && token_grammar[current_token_grammar_index+1] == TokenType::RightParenth
@@ -669,7 +1334,7 @@ impl Assembler
                 {
                     if !(operator.contains(&token_vec[i+1].token_type) || token_vec[i+1].token_type == TokenType::RightParenth || end.contains(&token_vec[i+1].token_type))
                     {
-                        return Err(Assembler::create_error("Syntax error", &token_vec[i+1], operand));
+                        return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[i+1], operand));
                     }
 
                     // this grammar doesn't match at all 
@@ -682,7 +1347,7 @@ impl Assembler
                     // the parenth is not apart of the grammar so this is an error
                     if left_parenth_count == 0 && token_grammar[current_token_grammar_index+1] != TokenType::RightParenth
                     {
-                        return Err(Assembler::create_error("Syntax error, unmatched right parenth", &token_vec[i+1], [operator.as_slice(), &[TokenType::RightParenth], end.as_slice()].concat()));
+                        return Err(Assembler::create_error(codes::UNMATCHED_PARENTH, "Unmatched right parenth", &token_vec[i+1], [operator.as_slice(), &[TokenType::RightParenth], end.as_slice()].concat()));
                     }
 
                     left_parenth_count = left_parenth_count -1;
@@ -695,12 +1360,12 @@ impl Assembler
                 {
                     if !(operand.contains(&token_vec[i+1].token_type) || token_vec[i+1].token_type == TokenType::LeftParenth)
                     {
-                        return Err(Assembler::create_error("Syntax error", &token_vec[i+1], [operand.as_slice(), &[TokenType::LeftParenth]].concat()));
+                        return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token_vec[i+1], [operand.as_slice(), &[TokenType::LeftParenth]].concat()));
                     }
                 }
                 else 
                 {
-                    return Err(Assembler::create_error("Syntax error", &token, [&[TokenType::LeftParenth], operand.as_slice()].concat()))
+                    return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token, [&[TokenType::LeftParenth], operand.as_slice()].concat()))
                 }
 
                 // its the end
@@ -711,7 +1376,7 @@ impl Assembler
 
                     if left_parenth_count >0
                     {
-                        return Err(Assembler::create_error("Syntax error, unmatched left parenth", &token_vec[i+1], vec![TokenType::RightParenth]));
+                        return Err(Assembler::create_error(codes::UNMATCHED_PARENTH, "Unmatched left parenth", &token_vec[i+1], vec![TokenType::RightParenth]));
                     }
 
 
@@ -741,12 +1406,12 @@ impl Assembler
     // unwrap_token_option
     // this function unwraps a token option
     // and creates an error if it gets nothing
-    fn unwrap_token_option(token:Option<Result<Token,GeneralError>>, iterator: &mut PeekWrapper<LexicalIterator>)->Result<Token,GeneralError>
+    fn unwrap_token_option(token:Option<Result<Token,Diagnostic>>, iterator: &mut PeekWrapper<LexicalIterator>)->Result<Token,Diagnostic>
     {
         let instrucion_token;
         match token
         {
-            None=>{ return Err(Assembler::create_error("Syntax Error, unpresidented eof. Or some other goofy error", &Token { token_type: TokenType::EOF, value: "".to_string(), logical_line: 0, file_line: iterator.iterator.analyzer.file_line }, vec![]))},
+            None=>{ return Err(Assembler::create_error(codes::SYNTAX, "Unexpected end of file", &Token { token_type: TokenType::EOF, value: "".to_string(), logical_line: 0, file_line: iterator.iterator.analyzer.file_line, column: 0, length: 0 }, vec![]))},
             Some(s) => { instrucion_token = s;}
         }
 
@@ -755,7 +1420,7 @@ impl Assembler
 
     // consume_if_available
     // consumes a token if it matches the given TokenType 
-    fn consume_if_available(token_type: TokenType, iterator: &mut PeekWrapper<LexicalIterator>)-> Result<(),GeneralError>
+    fn consume_if_available(token_type: TokenType, iterator: &mut PeekWrapper<LexicalIterator>)-> Result<(),Diagnostic>
     {
         let next_token_option = iterator.peek(0);
         let token:Token ;
@@ -785,40 +1450,30 @@ impl Assembler
     
     // cretae_error
     // with expected and recived tokens
-    fn create_error(error_description:&str, recieved:&Token, expected:Vec<TokenType>) ->GeneralError
+    fn create_error(code: &'static str, error_description:&str, recieved:&Token, expected:Vec<TokenType>) ->Diagnostic
     {
-        let mut expec= "[".to_string();
-        for i in expected
-        {
-            expec = expec + &i.to_string() + ", ";
-        }
-        expec = expec + "]";
-
-        let string = format!("{line}:{description}, expected: {expected:?}, recieved: {token}", line=recieved.file_line,description=error_description, expected=expec, token=recieved.to_string());
-
-        GeneralError::new(&string,"Assembler")
+        Diagnostic::at(code, error_description, "Assembler", recieved, expected)
     }
 
     // create_empty_error
     // doesn't have a recived or expected
     // this is used for errors that are assembler based
     // i.e. something broke in the assembler
-    fn create_empty_error(error_description:&str) ->GeneralError
+    fn create_empty_error(code: &'static str, error_description:&str) ->Diagnostic
     {
-        GeneralError::new(&error_description,"Assembler")
+        Diagnostic::new(code, error_description, "Assembler")
     }
 
-    // write_to_file
-    // writes to a given file a given token
-    // does different things based on the token type
-    fn write_token_to_file(file:&mut File, token: Token, symbol_table: &mut HashMap<String, InsertableNum>,) -> Result<(), GeneralError>
+    // token_to_bytes
+    // turns a given token into the bytes it
+    // stands for, does different things based on the token type
+    fn token_to_bytes(token: Token, symbol_table: &HashMap<String, InsertableNum>,) -> Result<Vec<u8>, Diagnostic>
     {   
-        let mut _result = Ok(0);
         match token.token_type
         {
             TokenType::Num1Bytes => 
             {
-                _result = file.write(&[Assembler::one_byte_num_string_to_int(token.value)]);
+                Ok(vec![Assembler::one_byte_num_string_to_int(token.value)])
             },
             TokenType::Num2Bytes =>
             {
@@ -830,7 +1485,11 @@ impl Assembler
                 let upper_byte:u8 = (two_byte_num >> 8) as u8;
 
                 // since it is little endian we store the lower byte first
-                _result = file.write(&[lower_byte, upper_byte]);
+                Ok(vec![lower_byte, upper_byte])
+            },
+            TokenType::Num3Bytes =>
+            {
+                Ok(InsertableNum::ThreeByte(Assembler::three_byte_num_string_to_int(token.value)).to_bytes())
             },
             TokenType::Label =>
             {
@@ -840,24 +1499,13 @@ impl Assembler
                 match insertable_num_option
                 {
                     None => {
-                        return Err(Assembler::create_error("Label doesn't exist", &token, vec![]))
+                        return Err(Assembler::create_error(codes::UNDEFINED_LABEL, "Label not defined", &token, vec![]))
                     }, 
                     Some(t) => insertable_num = *t,
                 }
 
-                // write it to file however so
-                match insertable_num 
-                {
-                    InsertableNum::Byte(num) => _result = file.write(&[num]),
-                    InsertableNum::TwoByte(num)  => 
-                    {
-                    // get the upper and lower bytes
-                    let lower_byte:u8 = num as u8;
-                    let upper_byte:u8 = (num >> 8) as u8;
-                    // since it is little endian we store the lower byte first
-                    _result = file.write(&[lower_byte, upper_byte]);
-                    }
-                }
+                // write it however so
+                Ok(insertable_num.to_bytes())
 
             },
             TokenType::Character =>
@@ -871,9 +1519,7 @@ impl Assembler
                 // get the character
                 let character = iter.next().unwrap();
 
-                // write the character to the file
-                _result = file.write(&[character as u8]);
-                
+                Ok(vec![character as u8])
             },
             TokenType::String =>
             {   
@@ -882,26 +1528,11 @@ impl Assembler
                 characters.next();
                 characters.next_back();
 
-                // write the string bytes 
-                _result = file.write(&characters.as_str().as_bytes());
+                // the string bytes 
+                Ok(characters.as_str().as_bytes().to_vec())
             },
-            _ => { 
-            }
+            _ => Ok(vec![])
         }
-
-
-
-        match _result {
-
-            Err(err)=> {
-                let error_string = format!("Problem writing to file. details: {:?}", err);
-                return Err(Assembler::create_empty_error(&error_string));
-            }
-
-            _=> Ok(())
-        }
-
-
     }
 
     // one_byte_num_string_to_int
@@ -940,6 +1571,22 @@ impl Assembler
         _returned
     }
 
+    // literal_truncated
+    // a warning if the token is a decimal
+    // number too big for two bytes
+    fn literal_truncated(token: &Token) -> Option<Diagnostic>
+    {
+        match token.value.parse::<u64>()
+        {
+            Ok(n) if n > 0xffff =>
+            {
+                let message = format!("Value {} doesn't fit in 2 bytes, truncated to ${:04X}", n, n as u16);
+                Some(Diagnostic::warning(Warning::ValueTruncated, &message, token))
+            }
+            _ => None,
+        }
+    }
+
     // two_byte_num_string_to_int
     // converts a two byte number
     // string to a u16
@@ -974,6 +1621,14 @@ impl Assembler
         _returned
     }
 
+    // three_byte_num_string_to_int
+    // converts a three byte number
+    // string to a u32, these are only ever hex
+    fn three_byte_num_string_to_int(num: String) -> u32
+    {
+        u32::from_str_radix(&num[1..], 16).unwrap()
+    }
+
 
     // possible directives for the assembler 
     ////////////////////////////////////////////////////////////////////////
@@ -984,7 +1639,7 @@ impl Assembler
     // and a list of bytes after it 
     // will store 2 bytes or 4 byte values 
     // witch can be labels, 
-    fn byte_directive_parser(assembler: &mut Assembler, first_pass:bool)-> Result<bool,GeneralError>
+    fn byte_directive_parser(assembler: &mut Assembler, first_pass:bool)-> Result<bool,Diagnostic>
     {
 
         // peek the token 
@@ -992,7 +1647,7 @@ impl Assembler
         let token;
         match token_option 
         {
-            None => return Err(Assembler::create_empty_error("Something bad happened in the byte_directive_parser")),
+            None => return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the byte_directive_parser")),
             Some(t)=> token = t?,
         }
 
@@ -1007,8 +1662,6 @@ impl Assembler
 
             let mut tokens: Vec<Token> = vec![];
 
-            let mut returned_bytes = 0;
-
             // while not at the end of the line 
             while current_token.token_type != TokenType::EOL 
             {
@@ -1016,34 +1669,60 @@ impl Assembler
                 Assembler::consume_if_available(TokenType::Comma, & mut assembler.lexical_iterator)?;
 
                  if current_token.token_type == TokenType::Character || current_token.token_type == TokenType::Num1Bytes || current_token.token_type == TokenType::String
+                    || current_token.token_type == TokenType::Num2Bytes || current_token.token_type == TokenType::Num3Bytes || current_token.token_type == TokenType::Label  
                 {
+                    if current_token.token_type == TokenType::Label
+                    {
+                        assembler.used_labels.insert(current_token.value.to_string());
+                    }
                     tokens.push(current_token);
-                    returned_bytes = returned_bytes+1;
-                }
-                else if current_token.token_type == TokenType::Num2Bytes || current_token.token_type == TokenType::Label  
-                {
-                    tokens.push(current_token);
-                    returned_bytes = returned_bytes+2;
                 }
                 else 
                 {
-                    return Err(Assembler::create_error("Syntax error", &current_token, vec![TokenType::Character, TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Label, TokenType::String]))
+                    return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &current_token, vec![TokenType::Character, TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Num3Bytes, TokenType::Label, TokenType::String]))
                 }
 
                 current_token = Assembler::unwrap_token_option(assembler.lexical_iterator.next(), &mut assembler.lexical_iterator)?;
             }
             
 
-           if !first_pass 
-           {
-                for token in tokens 
+            for token in tokens 
+            {
+                // labels defined further down aren't known on the first pass
+                // those can only be code labels so they are two bytes
+                if first_pass && token.token_type == TokenType::Label && !assembler.symbol_table.contains_key(&token.value)
                 {
-                Assembler::write_token_to_file(&mut assembler.file_writer, token, &mut assembler.symbol_table)?;
+                    assembler.current_byte += 2;
+                    continue;
                 }
-           }
 
+                // and one that isn't two bytes would have moved
+                // everything after it between the passes
+                let defined_later = token.token_type == TokenType::Label
+                    && assembler.symbol_lines.get(&token.value).is_some_and(|(line, _, _)| *line > token.file_line);
+
+                let truncated = Assembler::literal_truncated(&token);
+                let bytes = Assembler::token_to_bytes(token.clone(), &assembler.symbol_table)?;
 
-           assembler.current_byte = assembler.current_byte + returned_bytes;
+                if !first_pass && defined_later && bytes.len() != 2
+                {
+                    let message = format!("Label {} is {} bytes but is defined further down, .BYTE takes those as 2 bytes, define it before this line", token.value, bytes.len());
+                    return Err(Assembler::create_error(codes::VALUE_OUT_OF_RANGE, &message, &token, vec![]));
+                }
+
+                if first_pass
+                {
+                    assembler.current_byte += bytes.len() as u32;
+                }
+                else
+                {
+                    if let Some(warning) = truncated
+                    {
+                        assembler.warn(warning);
+                    }
+                    assembler.emit(&bytes);
+                }
+            }
 
            return Ok(true);
 
@@ -1057,7 +1736,7 @@ impl Assembler
     // will set the org 
     // of the current byte count
     // so that labels will be in relation to that 
-    fn org_directive_parser(assembler:&mut Assembler)-> Result<bool,GeneralError>
+    fn org_directive_parser(assembler:&mut Assembler)-> Result<bool,Diagnostic>
     {
 
          // peek the token 
@@ -1065,7 +1744,7 @@ impl Assembler
          let token;
          match token_option 
          {
-             None => return Err(Assembler::create_empty_error("Something bad happened in the org_directive_parser")),
+             None => return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the org_directive_parser")),
              Some(t)=> token = t?,
          }
  
@@ -1086,9 +1765,13 @@ impl Assembler
             {
                 assembler.current_byte = Assembler::two_byte_num_string_to_int(token.value) as u32;
             }
+            else if token.token_type == TokenType::Num3Bytes
+            {
+                assembler.current_byte = Assembler::three_byte_num_string_to_int(token.value);
+            }
             else 
             {
-                return Err(Assembler::create_error("Syntax error", &token, vec![TokenType::Num1Bytes, TokenType::Num2Bytes]))
+                return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &token, vec![TokenType::Num1Bytes, TokenType::Num2Bytes, TokenType::Num3Bytes]))
             }
 
 
@@ -1102,8 +1785,582 @@ impl Assembler
          Ok(false)
 
     }
-    
 
+    // start_directive_parser
+    // accepts .start or .START
+    // followed by a label or expression saying where
+    // execution begins, it is only worked out on the second
+    // pass so the label can be defined anywhere in the file
+    fn start_directive_parser(assembler:&mut Assembler, first_pass: bool)-> Result<bool,Diagnostic>
+    {
+         // peek the token 
+         let token_option = assembler.lexical_iterator.peek(0);
+         let token = match token_option 
+         {
+             None => return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the start_directive_parser")),
+             Some(t)=> t?,
+         };
+
+         if token.value.to_lowercase() == ".start"
+         {
+            // consume the .start
+            assembler.lexical_iterator.next();
+
+            let mut token_stack:Vec<Token> = vec![];
+            Assembler::get_until_eol(assembler, &mut token_stack)?;
+            Assembler::check_label_expression_syntax(&token_stack)?;
+
+            if !first_pass
+            {
+                let num = Assembler::expression(assembler, &token_stack)?;
+                assembler.object_code.start_address = Some(num.unwrap());
+            }
+
+            return Ok(true);
+         }
+
+         Ok(false)
+    }
+
+    // ines_directive_parser
+    // accepts .inesprg .ineschr .inesmap and .inesmir
+    // in any case, each followed by a value that
+    // goes into the NES rom header
+    fn ines_directive_parser(assembler:&mut Assembler, first_pass: bool)-> Result<bool,Diagnostic>
+    {
+         // peek the token 
+         let token_option = assembler.lexical_iterator.peek(0);
+         let token = match token_option 
+         {
+             None => return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the ines_directive_parser")),
+             Some(t)=> t?,
+         };
+
+         let directive = token.value.to_lowercase();
+         if !(directive == ".inesprg" || directive == ".ineschr" || directive == ".inesmap" || directive == ".inesmir")
+         {
+            return Ok(false);
+         }
+
+        // consume the directive
+        assembler.lexical_iterator.next();
+
+        let mut token_stack:Vec<Token> = vec![];
+        Assembler::get_until_eol(assembler, &mut token_stack)?;
+        Assembler::check_label_expression_syntax(&token_stack)?;
+
+        // the header only needs setting once
+        if first_pass
+        {
+            let value = Assembler::expression(assembler, &token_stack)?.unwrap();
+
+            if directive != ".inesmap" && value > 0xff
+            {
+                return Err(Assembler::create_error(codes::VALUE_OUT_OF_RANGE, "Value is too big for the header", &token, vec![TokenType::Num1Bytes]));
+            }
+
+            let header = &mut assembler.object_code.ines;
+            match directive.as_str()
+            {
+                ".inesprg" => header.prg_banks = value as u8,
+                ".ineschr" => header.chr_banks = Some(value as u8),
+                ".inesmap" => header.mapper = value as u16,
+                _ => header.mirroring = value as u8,
+            }
+        }
+
+        Ok(true)
+    }
+
+    // cpu_directive_parser
+    // accepts .cpu followed by the name of a cpu,
+    // or .p02, .pc02/.p65c02, .pwc02 and .p816 like ca65, everything
+    // after it is assembled for that cpu
+    fn cpu_directive_parser(assembler:&mut Assembler)-> Result<bool,Diagnostic>
+    {
+         // peek the token 
+         let token_option = assembler.lexical_iterator.peek(0);
+         let token = match token_option 
+         {
+             None => return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the cpu_directive_parser")),
+             Some(t)=> t?,
+         };
+
+         let cpu = match token.value.to_lowercase().as_str()
+         {
+            ".p02" => Cpu::Mos6502,
+            ".pc02" | ".p65c02" => Cpu::Cmos65C02,
+            ".pwc02" => Cpu::Wdc65C02,
+            ".p816" => Cpu::Wdc65816,
+            ".cpu" =>
+            {
+                // consume the .cpu
+                assembler.lexical_iterator.next();
+
+                // 6502 is a number and 65C02 a label, the text is what counts
+                let name = Assembler::unwrap_token_option(assembler.lexical_iterator.next(), &mut assembler.lexical_iterator)?;
+                match Cpu::from_name(&name.value)
+                {
+                    Some(cpu) => cpu,
+                    None =>
+                    {
+                        let names: Vec<String> = Cpu::all().iter().map(|c| c.to_string()).collect();
+                        return Err(Assembler::create_error(codes::SYNTAX, &format!("Unknown cpu, expected one of: {}", names.join(", ")), &name, vec![]));
+                    }
+                }
+            }
+            _ => return Ok(false),
+         };
+
+        // consume the .p02 or .pc02
+        if token.value.to_lowercase() != ".cpu"
+        {
+            assembler.lexical_iterator.next();
+        }
+
+        Assembler::consume_if_available(TokenType::EOL, &mut assembler.lexical_iterator)?;
+        assembler.set_cpu(cpu);
+
+        Ok(true)
+    }
+
+    // register_width_directive_parser
+    // accepts .a8, .a16, .i8 and .i16 which tell the
+    // assembler how wide the 65816 registers are, it can't
+    // follow REP and SEP so the code has to say
+    fn register_width_directive_parser(assembler:&mut Assembler)-> Result<bool,Diagnostic>
+    {
+         // peek the token 
+         let token_option = assembler.lexical_iterator.peek(0);
+         let token = match token_option 
+         {
+             None => return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the register_width_directive_parser")),
+             Some(t)=> t?,
+         };
+
+         match token.value.to_lowercase().as_str()
+         {
+            ".a8" => assembler.accumulator_16 = false,
+            ".a16" => assembler.accumulator_16 = true,
+            ".i8" => assembler.index_16 = false,
+            ".i16" => assembler.index_16 = true,
+            _ => return Ok(false),
+         }
+
+        // consume the directive
+        assembler.lexical_iterator.next();
+        Assembler::consume_if_available(TokenType::EOL, &mut assembler.lexical_iterator)?;
+
+        Ok(true)
+    }
+
+
+    // test_directive_parser
+    // skips a .TEST name ... .ENDTEST block, the
+    // test runner reads the lines itself, the labels
+    // in it count as used so routines only tests call
+    // don't get warned about
+    fn test_directive_parser(assembler:&mut Assembler, first_pass: bool)-> Result<bool,Diagnostic>
+    {
+        let token = match assembler.lexical_iterator.peek(0)
+        {
+            None => return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the test_directive_parser")),
+            Some(t)=> t?,
+        };
+
+        if token.value.to_lowercase() != ".test"
+        {
+            return Ok(false);
+        }
+
+        // consume the directive
+        assembler.lexical_iterator.next();
+
+        // every test has a name
+        let name = match assembler.lexical_iterator.next()
+        {
+            None => return Err(Assembler::create_empty_error(codes::INTERNAL, "Something bad happened in the test_directive_parser")),
+            Some(t) => t?,
+        };
+
+        loop
+        {
+            let next = match assembler.lexical_iterator.next()
+            {
+                None => return Err(Assembler::create_error(codes::SYNTAX, ".TEST without an .ENDTEST", &token, vec![])),
+                Some(t) => t?,
+            };
+
+            match next.token_type
+            {
+                TokenType::EOF => return Err(Assembler::create_error(codes::SYNTAX, ".TEST without an .ENDTEST", &token, vec![])),
+                TokenType::Directive if next.value.to_lowercase() == ".endtest" =>
+                {
+                    if first_pass && name.token_type == TokenType::Label
+                    {
+                        assembler.tests.push(TestBlock { name: name.value.to_string(), line: token.file_line, end_line: next.file_line });
+                    }
+                    break;
+                }
+                TokenType::Label => { assembler.used_labels.insert(next.value.to_string()); }
+                _ => (),
+            }
+        }
+
+        Assembler::consume_if_available(TokenType::EOL, &mut assembler.lexical_iterator)?;
+
+        // the block is skipped either way so
+        // its lines don't turn into more errors
+        if name.token_type != TokenType::Label
+        {
+            return Err(Assembler::create_error(codes::SYNTAX, "Syntax error", &name, vec![TokenType::Label]));
+        }
+
+        Ok(true)
+    }
 
 }
 
+
+#[cfg(test)]
+pub(crate) mod tests
+{
+    use super::*;
+
+    // assemble
+    // assembles the source from a temporary file and
+    // hands back every byte emitted or the diagnostics
+    pub(crate) fn assemble(name: &str, source: &str, options: AssemblerOptions) -> Result<Vec<u8>, Vec<Diagnostic>>
+    {
+        let file = std::env::temp_dir().join(format!("asm_6502_{}_{}.asm", name, std::process::id()));
+        std::fs::write(&file, source).unwrap();
+
+        let result = Assembler::without_output(file.to_str().unwrap(), options)
+            .map_err(|err| vec![err])
+            .and_then(|mut assembler| assembler.run().map(|_| assembler.object_code.segments.iter().flat_map(|s| s.bytes.clone()).collect()));
+
+        std::fs::remove_file(&file).ok();
+        result
+    }
+
+    // warnings
+    // assembles the source from a temporary file and
+    // hands back the warnings or the diagnostics
+    fn warnings(name: &str, source: &str, options: AssemblerOptions) -> Result<Vec<Diagnostic>, Vec<Diagnostic>>
+    {
+        let file = std::env::temp_dir().join(format!("asm_6502_{}_{}.asm", name, std::process::id()));
+        std::fs::write(&file, source).unwrap();
+
+        let result = Assembler::without_output(file.to_str().unwrap(), options)
+            .map_err(|err| vec![err])
+            .and_then(|mut assembler| assembler.run());
+
+        std::fs::remove_file(&file).ok();
+        result
+    }
+
+    // error_lines
+    // the line each error was on
+    fn error_lines(errors: &[Diagnostic]) -> Vec<u32>
+    {
+        errors.iter().map(|e| e.line).collect()
+    }
+
+    const FOUR_ERRORS: &str = ".ORG $0600\n  LDA #$1234\n  INX\n  LDX #$1234\n  INY\n  LDY #$1234\n  STA ($10\n  RTS\n";
+
+    #[test]
+    fn assembling_goes_on_after_the_first_error()
+    {
+        let errors = assemble("recovery", FOUR_ERRORS, AssemblerOptions::default()).unwrap_err();
+        assert_eq!(error_lines(&errors), vec![2, 4, 6, 7]);
+    }
+
+    #[test]
+    fn max_errors_stops_early()
+    {
+        let options = AssemblerOptions { max_errors: 2, ..AssemblerOptions::default() };
+        let errors = assemble("max_errors", FOUR_ERRORS, options).unwrap_err();
+        assert_eq!(error_lines(&errors), vec![2, 4]);
+    }
+
+    #[test]
+    fn max_errors_of_zero_never_stops()
+    {
+        let source = "  LDA #$1234\n".repeat(30);
+        let options = AssemblerOptions { max_errors: 0, ..AssemblerOptions::default() };
+        assert_eq!(assemble("no_max_errors", &source, options).unwrap_err().len(), 30);
+        assert_eq!(assemble("default_max_errors", &source, AssemblerOptions::default()).unwrap_err().len(), 20);
+    }
+
+    #[test]
+    fn clean_source_has_no_errors()
+    {
+        assert!(assemble("clean", ".ORG $0600\n  LDA #$01\n  RTS\n", AssemblerOptions::default()).is_ok());
+    }
+
+    const NEAR_BRANCH: &str = ".ORG $0600\n  BNE $0583\n";
+
+    #[test]
+    fn branches_near_the_limit_are_warned_about()
+    {
+        let warnings = warnings("branch_range", NEAR_BRANCH, AssemblerOptions::default()).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, codes::BRANCH_RANGE);
+        assert_eq!(warnings[0].line, 2);
+        assert!(!warnings[0].is_error());
+    }
+
+    #[test]
+    fn warnings_can_be_turned_off_or_into_errors()
+    {
+        let options = AssemblerOptions { warnings: vec![(Warning::BranchRange, WarningLevel::Allow)], ..AssemblerOptions::default() };
+        assert!(warnings("branch_range_off", NEAR_BRANCH, options).unwrap().is_empty());
+
+        let options = AssemblerOptions { warnings: vec![(Warning::BranchRange, WarningLevel::Deny)], ..AssemblerOptions::default() };
+        assert_eq!(warnings("branch_range_deny", NEAR_BRANCH, options).unwrap_err()[0].code, codes::BRANCH_RANGE);
+
+        let options = AssemblerOptions { warnings_as_errors: true, ..AssemblerOptions::default() };
+        assert!(warnings("branch_range_werror", NEAR_BRANCH, options).is_err());
+    }
+
+    #[test]
+    fn unused_labels_when_asked_for()
+    {
+        let source = ".ORG $0600\nstart:\n  NOP\nunused:\n  JMP start\n";
+        assert!(warnings("unused_default", source, AssemblerOptions::default()).unwrap().is_empty());
+
+        let options = AssemblerOptions { warnings: vec![(Warning::UnusedLabel, WarningLevel::Warn)], ..AssemblerOptions::default() };
+        let warnings = warnings("unused_on", source, options).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].code, codes::UNUSED_LABEL);
+        assert_eq!(warnings[0].line, 4);
+    }
+
+    // assert_needs_cpu
+    // the mnemonic on a line of its own is an error
+    // naming the cpu it needs, not a label
+    fn assert_needs_cpu(mnemonic: &str, cpu: &str)
+    {
+        let errors = assemble(mnemonic, &format!(".ORG $0600\n  {}\n  INX\n", mnemonic), AssemblerOptions::default()).unwrap_err();
+        assert_eq!(errors[0].code, codes::UNKNOWN_INSTRUCTION, "{}", mnemonic);
+        assert!(errors[0].message.contains(&format!(".CPU {}", cpu)), "{}: {}", mnemonic, errors[0].message);
+    }
+
+    #[test]
+    fn implied_65c02_instructions_need_the_cpu()
+    {
+        for mnemonic in ["PHX", "PLX", "PHY", "PLY"]
+        {
+            assert_needs_cpu(mnemonic, "65C02");
+        }
+    }
+
+    #[test]
+    fn accumulator_inc_and_dec_need_the_cpu()
+    {
+        for source in ["  INC\n", "  DEC\n"]
+        {
+            assert!(assemble("incdec", source, AssemblerOptions::default()).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn undocumented_instructions_need_the_cpu()
+    {
+        assert_needs_cpu("JAM", "6502X");
+    }
+
+    #[test]
+    fn wdc_instructions_need_the_cpu()
+    {
+        assert_needs_cpu("WAI", "W65C02");
+        assert_needs_cpu("STP", "W65C02");
+    }
+
+    #[test]
+    fn huc6280_instructions_need_the_cpu()
+    {
+        for mnemonic in ["CLA", "CLX", "CSH", "SAX"]
+        {
+            assert_needs_cpu(mnemonic, "HuC6280");
+        }
+    }
+
+    #[test]
+    fn implied_instructions_work_with_the_cpu()
+    {
+        let options = AssemblerOptions { cpu: Cpu::Cmos65C02, ..AssemblerOptions::default() };
+        assert_eq!(assemble("phx_65c02", "  PHX\n  INX\n", options).unwrap(), vec![0xda, 0xe8]);
+    }
+
+    #[test]
+    fn other_mnemonics_are_still_labels_with_a_colon_or_equals()
+    {
+        assert_eq!(assemble("phx_label", ".ORG $0600\nphx:\n  INX\n  JMP phx\n", AssemblerOptions::default()).unwrap(), vec![0xe8, 0x4c, 0x00, 0x06]);
+        assert_eq!(assemble("phx_constant", "phx = $10\n  LDA phx\n", AssemblerOptions::default()).unwrap(), vec![0xa5, 0x10]);
+    }
+
+    #[test]
+    fn byte_takes_forward_code_labels()
+    {
+        let bytes = assemble("byte_forward", ".ORG $0600\n  .BYTE start, $01\nstart:\n  RTS\n", AssemblerOptions::default()).unwrap();
+        assert_eq!(bytes, vec![0x03, 0x06, 0x01, 0x60]);
+    }
+
+    #[test]
+    fn byte_forward_label_of_another_size_is_an_error()
+    {
+        let errors = assemble("byte_forward_size", ".ORG $0600\n  .BYTE small\nsmall = $10\n", AssemblerOptions::default()).unwrap_err();
+        assert_eq!(errors[0].code, codes::VALUE_OUT_OF_RANGE);
+        assert!(errors[0].message.contains("small"), "{}", errors[0].message);
+    }
+
+    #[test]
+    fn werror_still_reports_second_pass_errors()
+    {
+        let mut options = AssemblerOptions::default();
+        options.warnings_as_errors = true;
+
+        let errors = assemble("werror", ".ORG $0600\nphx:\n  JMP phx\n  .BYTE small\nsmall = $10\n", options).unwrap_err();
+        let codes: Vec<&str> = errors.iter().filter(|e| e.is_error()).map(|e| e.code).collect();
+        assert!(codes.contains(&codes::LABEL_SHADOWS_MNEMONIC), "{:?}", codes);
+        assert!(codes.contains(&codes::VALUE_OUT_OF_RANGE), "{:?}", codes);
+    }
+
+    #[test]
+    fn operands_ask_only_for_sizes_the_modes_take()
+    {
+        let mut options = AssemblerOptions::default();
+        options.cpu = Cpu::Cmos65C02;
+        let errors = assemble("expected_65c02", ".ORG $0600\n  STZ\n", options).unwrap_err();
+        assert!(!errors[0].expected.contains(&TokenType::Num3Bytes), "{}", errors[0].expected_words());
+        assert!(errors[0].expected.contains(&TokenType::Num2Bytes), "{}", errors[0].expected_words());
+
+        let mut options = AssemblerOptions::default();
+        options.cpu = Cpu::Wdc65816;
+        let errors = assemble("expected_65816", ".ORG $0600\n  LDA\n", options).unwrap_err();
+        assert!(errors[0].expected.contains(&TokenType::Num3Bytes), "{}", errors[0].expected_words());
+    }
+
+    #[test]
+    fn duplicate_label_note_points_at_the_first_label()
+    {
+        let errors = assemble("duplicate", ".ORG $0600\n  start:\n  NOP\nstart:\n  JMP start\n", AssemblerOptions::default()).unwrap_err();
+        assert_eq!(errors[0].code, codes::DUPLICATE_LABEL);
+        let note = &errors[0].notes[0];
+        assert_eq!(note.line, 2);
+        assert_eq!(note.columns, Some((3, 8)));
+    }
+
+    #[test]
+    fn branches_wrap_around_the_bank()
+    {
+        let bytes = assemble("branch_wrap", ".ORG $FFFC\n  BEQ $0002\n  NOP\n  NOP\n", AssemblerOptions::default()).unwrap();
+        assert_eq!(bytes, vec![0xf0, 0x04, 0xea, 0xea]);
+    }
+
+    #[test]
+    fn s_is_a_label_on_the_6502()
+    {
+        let bytes = assemble("s_label", ".ORG $0600\n  LDX #1\ns:\n  JMP s\n", AssemblerOptions::default()).unwrap();
+        assert_eq!(bytes, vec![0xa2, 0x01, 0x4c, 0x02, 0x06]);
+    }
+
+    #[test]
+    fn stack_relative_needs_the_65816()
+    {
+        let errors = assemble("stack_relative_6502", ".ORG $0600\n  LDA $03,S\n", AssemblerOptions::default()).unwrap_err();
+        assert_eq!(errors[0].code, codes::UNKNOWN_INSTRUCTION);
+        assert!(errors[0].message.contains(".CPU 65816"), "{}", errors[0].message);
+    }
+
+    #[test]
+    fn stack_relative_on_the_65816()
+    {
+        let options = AssemblerOptions { cpu: Cpu::Wdc65816, ..AssemblerOptions::default() };
+        let source = ".ORG $0600\ns:\n  LDA $03,S\n  LDA ($05,s),Y\n  JMP s\n";
+        assert_eq!(assemble("stack_relative_65816", source, options).unwrap(), vec![0xa3, 0x03, 0xb3, 0x05, 0x4c, 0x00, 0x06]);
+    }
+
+    // assemble_65816
+    // assembles the source for the 65816
+    fn assemble_65816(name: &str, source: &str) -> Result<Vec<u8>, Vec<Diagnostic>>
+    {
+        assemble(name, source, AssemblerOptions { cpu: Cpu::Wdc65816, ..AssemblerOptions::default() })
+    }
+
+    #[test]
+    fn long_addresses_on_the_65816()
+    {
+        let bytes = assemble_65816("long", ".ORG $0600\n  LDA $7E2000\n  STA $7E2000,X\n  JML $123456\n  JSL $123456\n").unwrap();
+        assert_eq!(bytes, vec![0xaf, 0x00, 0x20, 0x7e, 0x9f, 0x00, 0x20, 0x7e, 0x5c, 0x56, 0x34, 0x12, 0x22, 0x56, 0x34, 0x12]);
+    }
+
+    #[test]
+    fn indirect_long_on_the_65816()
+    {
+        let bytes = assemble_65816("indirect_long", ".ORG $0600\n  LDA [$10]\n  LDA [$10],Y\n  JML [$1234]\n").unwrap();
+        assert_eq!(bytes, vec![0xa7, 0x10, 0xb7, 0x10, 0xdc, 0x34, 0x12]);
+    }
+
+    #[test]
+    fn block_moves_put_the_destination_bank_first()
+    {
+        let bytes = assemble_65816("block_move", ".ORG $0600\n  MVN $01,$02\n  MVP $7E,$7F\n").unwrap();
+        assert_eq!(bytes, vec![0x54, 0x02, 0x01, 0x44, 0x7f, 0x7e]);
+    }
+
+    #[test]
+    fn register_widths_set_the_immediate_size()
+    {
+        let source = ".ORG $0600\n.A16\n  LDA #$1234\n  LDX #$10\n.I16\n  LDY #$0010\n.A8\n  LDA #$12\n.I8\n  LDX #$10\n";
+        assert_eq!(assemble_65816("widths", source).unwrap(), vec![0xa9, 0x34, 0x12, 0xa2, 0x10, 0xa0, 0x10, 0x00, 0xa9, 0x12, 0xa2, 0x10]);
+    }
+
+    #[test]
+    fn register_widths_only_matter_on_the_65816()
+    {
+        let errors = assemble("widths_6502", ".ORG $0600\n.A16\n  LDA #$1234\n", AssemblerOptions::default()).unwrap_err();
+        assert_eq!(errors[0].line, 3);
+    }
+
+    // assemble_rockwell
+    // assembles the source for the Rockwell 65C02
+    fn assemble_rockwell(name: &str, source: &str) -> Result<Vec<u8>, Vec<Diagnostic>>
+    {
+        assemble(name, source, AssemblerOptions { cpu: Cpu::Rockwell65C02, ..AssemblerOptions::default() })
+    }
+
+    #[test]
+    fn reset_and_set_memory_bits()
+    {
+        let bytes = assemble_rockwell("rmb_smb", ".ORG $0600\n  RMB0 $10\n  RMB3 $10\n  SMB5 $10\n  SMB7 $FF\n").unwrap();
+        assert_eq!(bytes, vec![0x07, 0x10, 0x37, 0x10, 0xd7, 0x10, 0xf7, 0xff]);
+    }
+
+    #[test]
+    fn bit_branches_backward_and_forward()
+    {
+        let bytes = assemble_rockwell("bbr_back", ".ORG $0600\nloop:\n  BBR0 $20,loop\n").unwrap();
+        assert_eq!(bytes, vec![0x0f, 0x20, 0xfd]);
+
+        // labels have to be defined before they're used, so the target is a number
+        let bytes = assemble_rockwell("bbs_forward", ".ORG $0600\n  BBS7 $20,$0604\n  NOP\n  RTS\n").unwrap();
+        assert_eq!(bytes, vec![0xff, 0x20, 0x01, 0xea, 0x60]);
+    }
+
+    #[test]
+    fn bit_branch_out_of_range()
+    {
+        let errors = assemble_rockwell("bbr_range", ".ORG $0600\n  BBR0 $20,$0700\n").unwrap_err();
+        assert_eq!(errors[0].code, codes::VALUE_OUT_OF_RANGE);
+        assert_eq!(errors[0].line, 2);
+    }
+
+    #[test]
+    fn bit_instructions_need_a_zero_page_operand()
+    {
+        assert!(assemble_rockwell("rmb_absolute", ".ORG $0600\n  RMB0 $1234\n").is_err());
+        assert!(assemble_rockwell("bbr_absolute", ".ORG $0600\nloop:\n  BBR0 $1234,loop\n").is_err());
+    }
+}
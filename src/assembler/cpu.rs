@@ -0,0 +1,98 @@
+use std::fmt;
+
+
+// Cpu
+// the processors the assembler can
+// write code for, picked with .CPU or --cpu
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Default)]
+pub enum Cpu
+{
+    #[default]
+    Mos6502,        // the original NMOS 6502
+    Mos6502X,       // the NMOS 6502 with its undocumented opcodes
+    Cmos65C02,      // the CMOS 65C02
+    Rockwell65C02,  // the 65C02 plus the Rockwell bit instructions
+    Wdc65C02,       // the Rockwell one plus WAI and STP
+    Wdc65816,       // the 16 bit 65816, 65C02 code runs on it but not the bit instructions
+    Ricoh2A03,      // the NES cpu, a 6502 with decimal mode taken out
+    HuC6280,        // the PC Engine cpu, a Rockwell 65C02 with more instructions
+}
+
+impl Cpu
+{
+    // all
+    // every cpu there is
+    pub fn all() -> Vec<Cpu>
+    {
+        vec![Cpu::Mos6502, Cpu::Mos6502X, Cpu::Cmos65C02, Cpu::Rockwell65C02, Cpu::Wdc65C02, Cpu::Wdc65816, Cpu::Ricoh2A03, Cpu::HuC6280]
+    }
+
+    // from_name
+    // the cpu for a name given to .CPU
+    // or --cpu, case doesn't matter
+    pub fn from_name(name: &str) -> Option<Cpu>
+    {
+        match name.to_lowercase().as_str()
+        {
+            "6502" | "nmos" => Some(Cpu::Mos6502),
+            "6502x" => Some(Cpu::Mos6502X),
+            "65c02" | "cmos" => Some(Cpu::Cmos65C02),
+            "r65c02" | "rockwell" => Some(Cpu::Rockwell65C02),
+            "w65c02" | "wdc" => Some(Cpu::Wdc65C02),
+            "65816" | "65c816" => Some(Cpu::Wdc65816),
+            "2a03" | "nes" => Some(Cpu::Ricoh2A03),
+            "huc6280" | "6280" | "pce" => Some(Cpu::HuC6280),
+            _ => None,
+        }
+    }
+
+    // instruction_sets
+    // the sets of the instruction table it has
+    pub fn instruction_sets(&self) -> &'static [&'static str]
+    {
+        match self
+        {
+            Cpu::Mos6502 | Cpu::Ricoh2A03 => &["6502"],
+            Cpu::Mos6502X => &["6502", "6502x"],
+            Cpu::Cmos65C02 => &["6502", "65c02"],
+            Cpu::Rockwell65C02 => &["6502", "65c02", "rockwell"],
+            Cpu::Wdc65C02 => &["6502", "65c02", "rockwell", "wdc"],
+            Cpu::Wdc65816 => &["6502", "65c02", "wdc", "65816"],
+            Cpu::HuC6280 => &["6502", "65c02", "rockwell", "huc6280"],
+        }
+    }
+
+    // is_65816
+    // true if it has the long addresses, the
+    // stack relative modes and 16 bit registers
+    pub fn is_65816(&self) -> bool
+    {
+        matches!(self, Cpu::Wdc65816)
+    }
+
+    // has_decimal_mode
+    // false if SED doesn't make ADC and SBC work in decimal
+    pub fn has_decimal_mode(&self) -> bool
+    {
+        !matches!(self, Cpu::Ricoh2A03)
+    }
+}
+
+impl fmt::Display for Cpu {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cpu::Mos6502 => write!(f, "6502"),
+            Cpu::Mos6502X => write!(f, "6502X"),
+            Cpu::Cmos65C02 => write!(f, "65C02"),
+            Cpu::Rockwell65C02 => write!(f, "R65C02"),
+            Cpu::Wdc65C02 => write!(f, "W65C02"),
+            Cpu::Wdc65816 => write!(f, "65816"),
+            Cpu::Ricoh2A03 => write!(f, "2A03"),
+            Cpu::HuC6280 => write!(f, "HuC6280"),
+        }
+    }
+}
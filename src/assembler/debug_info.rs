@@ -0,0 +1,99 @@
+
+use crate::assembler::listing::SourceLine;
+use crate::assembler::symbol_file::Symbol;
+use crate::assembler::object_code::ObjectCode;
+use crate::assembler::json;
+
+
+// the version of the debug info format,
+// bump it when a field changes meaning
+const VERSION: u32 = 1;
+
+
+// debug_info
+// writes the debug info json that maps every emitted
+// byte range back to the line it came from, see the
+// readme for the format
+pub fn debug_info(file_name: &str, lines: &[SourceLine], symbols: &[Symbol], object_code: &ObjectCode) -> String
+{
+    let files = vec![json::object(&[
+        ("id", "0".to_string()),
+        ("name", json::string(file_name)),
+    ])];
+
+    let ranges: Vec<String> = lines.iter()
+        .filter(|l| !l.bytes.is_empty())
+        .map(|l| json::object(&[
+            ("file", "0".to_string()),
+            ("line", l.file_line.to_string()),
+            ("start", l.address.unwrap_or(0).to_string()),
+            ("size", l.bytes.len().to_string()),
+        ])).collect();
+
+    // there are no .PROC or .SCOPE directives so
+    // the whole file is the one scope
+    let scopes = vec![json::object(&[
+        ("id", "0".to_string()),
+        ("name", json::string("")),
+        ("file", "0".to_string()),
+        ("start", object_code.lowest_address().unwrap_or(0).to_string()),
+        ("end", object_code.highest_address().unwrap_or(0).to_string()),
+    ])];
+
+    let symbols: Vec<String> = symbols.iter().map(|s| json::object(&[
+        ("name", json::string(&s.name)),
+        ("value", s.value.unwrap().to_string()),
+        ("type", json::string(if s.constant { "constant" } else { "label" })),
+        ("size", s.value.size().to_string()),
+        ("scope", "0".to_string()),
+        ("file", "0".to_string()),
+        ("line", s.line.to_string()),
+    ])).collect();
+
+    json::object(&[
+        ("version", VERSION.to_string()),
+        ("files", json::array(&files)),
+        ("lines", json::array(&ranges)),
+        ("scopes", json::array(&scopes)),
+        ("symbols", json::array(&symbols)),
+        ("macros", json::array(&[])),
+        ("entry", object_code.entry_point().to_string()),
+    ]) + "\n"
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use crate::assembler::insertable_num::InsertableNum;
+
+    #[test]
+    fn bytes_map_back_to_their_lines()
+    {
+        let lines = vec![
+            SourceLine { file_line: 1, address: None, bytes: vec![] },
+            SourceLine { file_line: 2, address: Some(0x0600), bytes: vec![0xa9, 0x01] },
+            SourceLine { file_line: 3, address: Some(0x0602), bytes: vec![0x8d, 0x00, 0x02] },
+        ];
+        let symbols = vec![
+            Symbol { name: "start".to_string(), value: InsertableNum::TwoByte(0x0600), constant: false, line: 2 },
+            Symbol { name: "size".to_string(), value: InsertableNum::Byte(5), constant: true, line: 1 },
+        ];
+
+        let mut object_code = ObjectCode::new();
+        object_code.write(0x0600, &[0xa9, 0x01, 0x8d, 0x00, 0x02]);
+
+        assert_eq!(debug_info("game.asm", &lines, &symbols, &object_code), concat!(
+            "{\"version\":1,",
+            "\"files\":[{\"id\":0,\"name\":\"game.asm\"}],",
+            "\"lines\":[{\"file\":0,\"line\":2,\"start\":1536,\"size\":2},{\"file\":0,\"line\":3,\"start\":1538,\"size\":3}],",
+            "\"scopes\":[{\"id\":0,\"name\":\"\",\"file\":0,\"start\":1536,\"end\":1541}],",
+            "\"symbols\":[",
+            "{\"name\":\"start\",\"value\":1536,\"type\":\"label\",\"size\":2,\"scope\":0,\"file\":0,\"line\":2},",
+            "{\"name\":\"size\",\"value\":5,\"type\":\"constant\",\"size\":1,\"scope\":0,\"file\":0,\"line\":1}],",
+            "\"macros\":[],",
+            "\"entry\":1536}\n"));
+    }
+}
@@ -0,0 +1,621 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::assembler::lexical_analyzer::{Token, TokenType};
+use crate::assembler::warning::Warning;
+use crate::assembler::json;
+
+
+// codes
+// stable codes for everything the assembler
+// can report, tools can match on these
+// instead of the message text
+pub mod codes
+{
+    pub const SYNTAX: &str = "E0001";
+    pub const UNKNOWN_INSTRUCTION: &str = "E0002";
+    pub const UNKNOWN_DIRECTIVE: &str = "E0003";
+    pub const UNDEFINED_LABEL: &str = "E0004";
+    pub const DUPLICATE_LABEL: &str = "E0005";
+    pub const DIVIDE_BY_ZERO: &str = "E0006";
+    pub const UNMATCHED_PARENTH: &str = "E0007";
+    pub const VALUE_OUT_OF_RANGE: &str = "E0008";
+    pub const IO: &str = "E0100";
+    pub const OUTPUT: &str = "E0200";
+    pub const INSTRUCTION_TABLE: &str = "E0300";
+    pub const TEST_FAILED: &str = "E0400";
+    pub const UNSUPPORTED_CPU: &str = "E0500";
+    pub const INTERNAL: &str = "E0900";
+    pub const IMPLICIT_UPCAST: &str = "W0001";
+    pub const UNUSED_LABEL: &str = "W0002";
+    pub const VALUE_TRUNCATED: &str = "W0003";
+    pub const BRANCH_RANGE: &str = "W0004";
+    pub const LABEL_SHADOWS_MNEMONIC: &str = "W0005";
+    pub const NO_DECIMAL_MODE: &str = "W0006";
+    pub const NOTE: &str = "N0000";
+}
+
+
+// Severity
+// how bad a diagnostic is
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum Severity
+{
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+
+// Diagnostic
+// anything the assembler reports, an error
+// from any part of it or a note attached to one,
+// has everything a tool needs to point at the problem
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Diagnostic
+{
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub from: String,                   // the part of the assembler it came from
+    pub file: Option<String>,
+    pub line: u32,                      // the file line, 0 when it isn't about a line
+    pub columns: Option<(u32, u32)>,    // start and end column on the line
+    pub expected: Vec<TokenType>,
+    pub found: Option<Token>,
+    pub notes: Vec<Diagnostic>,         // related notes, i.e. where a label was first defined
+}
+
+impl Diagnostic
+{
+    // new
+    // an error that isn't about
+    // any particular line
+    pub fn new(code: &'static str, msg: &str, frm: &str) -> Diagnostic
+    {
+        Diagnostic
+        {
+            severity: Severity::Error,
+            code,
+            message: msg.to_string(),
+            from: frm.to_string(),
+            file: None,
+            line: 0,
+            columns: None,
+            expected: vec![],
+            found: None,
+            notes: vec![],
+        }
+    }
+
+    // at
+    // an error about the given token
+    // with the tokens that would have been fine
+    pub fn at(code: &'static str, msg: &str, frm: &str, found: &Token, expected: Vec<TokenType>) -> Diagnostic
+    {
+        let mut diagnostic = Diagnostic::new(code, msg, frm);
+        diagnostic.line = found.file_line;
+        if found.column != 0
+        {
+            diagnostic.columns = Some((found.column, found.column + found.length));
+        }
+        diagnostic.expected = expected;
+        diagnostic.found = Some(found.clone());
+        diagnostic
+    }
+
+    // warning
+    // a warning about the given token,
+    // the assembler decides if it is shown
+    pub fn warning(warning: Warning, msg: &str, found: &Token) -> Diagnostic
+    {
+        let mut diagnostic = Diagnostic::at(warning.code(), msg, "Assembler", found, vec![]);
+        diagnostic.severity = Severity::Warning;
+        diagnostic
+    }
+
+    // note
+    // a note about a line, meant to
+    // be attached to another diagnostic
+    pub fn note(msg: &str, line: u32) -> Diagnostic
+    {
+        let mut diagnostic = Diagnostic::new(codes::NOTE, msg, "Assembler");
+        diagnostic.severity = Severity::Note;
+        diagnostic.line = line;
+        diagnostic
+    }
+
+    // with_note
+    // attaches a note
+    pub fn with_note(mut self, note: Diagnostic) -> Diagnostic
+    {
+        self.notes.push(note);
+        self
+    }
+
+    // expected_words
+    // the expected tokens in words, a comma followed
+    // by a register reads as ,X or ,Y
+    // i.e. "`,X`, `,Y` or end of line"
+    pub fn expected_words(&self) -> String
+    {
+        let mut words: Vec<String> = vec![];
+
+        let mut i = 0;
+        while i < self.expected.len()
+        {
+            let t = self.expected[i];
+            let next = self.expected.get(i + 1);
+
+            if t == TokenType::Comma && next == Some(&TokenType::RegX)
+            {
+                words.push("`,X`".to_string());
+                i += 1;
+            }
+            else if t == TokenType::Comma && next == Some(&TokenType::RegY)
+            {
+                words.push("`,Y`".to_string());
+                i += 1;
+            }
+            else if t == TokenType::Comma && next == Some(&TokenType::RegS)
+            {
+                words.push("`,S`".to_string());
+                i += 1;
+            }
+            else if !words.contains(&t.describe().to_string())
+            {
+                words.push(t.describe().to_string());
+            }
+            i += 1;
+        }
+
+        match words.len()
+        {
+            0 => "".to_string(),
+            1 => words[0].to_string(),
+            n => format!("{} or {}", words[..n-1].join(", "), words[n-1]),
+        }
+    }
+
+    // render
+    // shows the diagnostic the way rustc does, the location,
+    // the source line and a caret under the token, source is
+    // the whole file it is about if it could be read
+    pub fn render(&self, source: Option<&str>, color: bool) -> String
+    {
+        let paint = |code: &str, text: &str| if color { format!("\x1b[{}m{}\x1b[0m", code, text) } else { text.to_string() };
+        let severity_color = match self.severity
+        {
+            Severity::Error => "1;31",
+            Severity::Warning => "1;33",
+            Severity::Note => "1;36",
+        };
+
+        // error[E0001]: Syntax error
+        let mut out = if self.severity == Severity::Note
+        {
+            paint(severity_color, &self.severity.to_string())
+        }
+        else
+        {
+            paint(severity_color, &format!("{}[{}]", self.severity, self.code))
+        };
+        out += &paint("1", &format!(": {}", self.message));
+        out += "\n";
+
+        let source_line = source.and_then(|s| s.lines().nth((self.line as usize).wrapping_sub(1)));
+        let columns = self.columns.or_else(|| source_line.map(|l| self.guess_columns(l)));
+
+        // --> file:line:col
+        if let Some(file) = &self.file
+        {
+            let gutter = " ".repeat(self.line.to_string().len());
+            match columns
+            {
+                Some((start, _)) if self.line != 0 => out += &format!("{}{} {}:{}:{}\n", gutter, paint("1;34", "-->"), file, self.line, start),
+                _ if self.line != 0 => out += &format!("{}{} {}:{}\n", gutter, paint("1;34", "-->"), file, self.line),
+                _ => out += &format!("{}{} {}\n", gutter, paint("1;34", "-->"), file),
+            }
+        }
+
+        //   |
+        // 3 | LDA ,X
+        //   |     ^ expected ...
+        if let Some(text) = source_line
+        {
+            let number = self.line.to_string();
+            let gutter = " ".repeat(number.len());
+            let bar = paint("1;34", "|");
+
+            out += &format!("{} {}\n", gutter, bar);
+            out += &format!("{} {} {}\n", paint("1;34", &number), bar, text.trim_end());
+
+            let mut label = self.expected_words();
+            if !label.is_empty()
+            {
+                label = format!("expected {}", label);
+                if let Some(found) = &self.found
+                {
+                    label += &format!(", found {}", found_words(found));
+                }
+            }
+
+            if let Some((start, end)) = columns
+            {
+                let indent: String = text.chars().take(start.saturating_sub(1) as usize)
+                    .map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+                let carets = "^".repeat((end.saturating_sub(start) as usize).max(1));
+
+                out += &format!("{} {} {}{}", gutter, bar, indent, paint(severity_color, &carets));
+                if !label.is_empty()
+                {
+                    out += &format!(" {}", paint(severity_color, &label));
+                }
+                out += "\n";
+            }
+            else if !label.is_empty()
+            {
+                out += &format!("{} {} {}\n", gutter, bar, paint(severity_color, &label));
+            }
+        }
+        else if !self.expected.is_empty()
+        {
+            out += &format!("  expected {}\n", self.expected_words());
+        }
+
+        for note in &self.notes
+        {
+            out += &note.render(source, color);
+        }
+
+        out
+    }
+
+    // guess_columns
+    // works out where the found token is on the
+    // line by looking for its text, used when the
+    // token didn't come from the lexer, columns start
+    // at 1 and the end is one past the last character
+    fn guess_columns(&self, line: &str) -> (u32, u32)
+    {
+        // everything before a comment
+        let code = line.split(';').next().unwrap_or("").trim_end();
+
+        match &self.found
+        {
+            Some(found) if !found.value.is_empty() =>
+            {
+                match code.find(&found.value)
+                {
+                    Some(i) =>
+                    {
+                        let start = code[..i].chars().count() as u32 + 1;
+                        (start, start + found.value.chars().count() as u32)
+                    }
+                    None => (1, code.chars().count() as u32 + 1),
+                }
+            }
+            // EOL and EOF point just past the code
+            Some(_) =>
+            {
+                let end = code.chars().count() as u32 + 1;
+                (end, end + 1)
+            }
+            // nothing to go on, underline the whole line
+            None =>
+            {
+                let start = (code.chars().count() - code.trim_start().chars().count()) as u32 + 1;
+                (start, code.chars().count() as u32 + 1)
+            }
+        }
+    }
+
+    // json
+    // the diagnostic as one line of json for
+    // editors and ci, see the readme for the fields
+    pub fn json(&self) -> String
+    {
+        let (column, end_column) = self.columns.unwrap_or((0, 0));
+        let expected: Vec<String> = self.expected.iter().map(|t| json::string(&format!("{:?}", t))).collect();
+        let notes: Vec<String> = self.notes.iter().map(|n| n.json()).collect();
+
+        json::object(&[
+            ("type", json::string("diagnostic")),
+            ("file", self.file.as_ref().map(|f| json::string(f)).unwrap_or("null".to_string())),
+            ("line", self.line.to_string()),
+            ("column", column.to_string()),
+            ("end_column", end_column.to_string()),
+            ("severity", json::string(&self.severity.to_string())),
+            ("code", json::string(self.code)),
+            ("message", json::string(&self.message)),
+            ("expected", json::array(&expected)),
+            ("expected_text", json::string(&self.expected_words())),
+            ("found", self.found.as_ref().map(|t| json::string(&t.value)).unwrap_or("null".to_string())),
+            ("notes", json::array(&notes)),
+        ])
+    }
+
+    // is_error
+    // true if this should fail the assembly
+    pub fn is_error(&self) -> bool
+    {
+        self.severity == Severity::Error
+    }
+}
+
+impl Error for Diagnostic {}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(file) = &self.file
+        {
+            write!(f, "{}:", file)?;
+        }
+        if self.line != 0
+        {
+            write!(f, "{}:", self.line)?;
+        }
+        if self.file.is_some() || self.line != 0
+        {
+            write!(f, " ")?;
+        }
+
+        // notes don't need a code, they hang off another diagnostic
+        if self.severity == Severity::Note
+        {
+            write!(f, "{}: {}", self.severity, self.message)?;
+        }
+        else
+        {
+            write!(f, "{}[{}]: {}", self.severity, self.code, self.message)?;
+        }
+
+        if !self.expected.is_empty()
+        {
+            let expected: Vec<String> = self.expected.iter().map(|t| t.to_string()).collect();
+            write!(f, ", expected: [{}]", expected.join(", "))?;
+        }
+        if let Some(found) = &self.found
+        {
+            write!(f, ", recieved: {}", found)?;
+        }
+
+        for note in &self.notes
+        {
+            write!(f, "\n  {}", note)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Summary
+// how an assembly went, counted
+// up from what it reported
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Summary
+{
+    pub errors: usize,
+    pub warnings: usize,
+    pub success: bool,
+    pub stopped_early: bool,    // it hit --max-errors
+}
+
+impl Summary
+{
+    // new
+    // counts the diagnostics, max_errors
+    // is the limit the assembly ran with
+    pub fn new(diagnostics: &[Diagnostic], success: bool, max_errors: usize) -> Summary
+    {
+        let errors = diagnostics.iter().filter(|d| d.is_error()).count();
+        let warnings = diagnostics.iter().filter(|d| d.severity == Severity::Warning).count();
+
+        Summary
+        {
+            errors,
+            warnings,
+            success,
+            stopped_early: max_errors != 0 && errors >= max_errors,
+        }
+    }
+
+    // text
+    // i.e. "2 errors, 1 warning" then
+    // Success! on its own line if it worked
+    pub fn text(&self) -> String
+    {
+        let mut counts: Vec<String> = vec![];
+        if self.errors > 0
+        {
+            counts.push(format!("{} error{}", self.errors, if self.errors == 1 { "" } else { "s" }));
+        }
+        if self.warnings > 0
+        {
+            counts.push(format!("{} warning{}", self.warnings, if self.warnings == 1 { "" } else { "s" }));
+        }
+
+        let mut text = counts.join(", ");
+        if self.success
+        {
+            if !text.is_empty()
+            {
+                text += "\n";
+            }
+            text += "Success!";
+        }
+        else if self.stopped_early
+        {
+            text += ", stopped early (see --max-errors)";
+        }
+
+        text
+    }
+
+    // json
+    // the summary as one line of json
+    pub fn json(&self) -> String
+    {
+        json::object(&[
+            ("type", json::string("summary")),
+            ("success", self.success.to_string()),
+            ("errors", self.errors.to_string()),
+            ("warnings", self.warnings.to_string()),
+            ("stopped_early", self.stopped_early.to_string()),
+        ])
+    }
+}
+
+// found_words
+// the found token for a message,
+// its text if it has any
+fn found_words(found: &Token) -> String
+{
+    if found.value.is_empty()
+    {
+        found.token_type.describe().to_string()
+    }
+    else
+    {
+        format!("`{}`", found.value)
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn errors_without_a_line()
+    {
+        let diagnostic = Diagnostic::new(codes::IO, "could not open game.asm", "Lexer");
+        assert!(diagnostic.is_error());
+        assert_eq!(diagnostic.to_string(), "error[E0100]: could not open game.asm");
+    }
+
+    #[test]
+    fn errors_at_a_token()
+    {
+        let mut found = Token::empty_token();
+        found.token_type = TokenType::Label;
+        found.value = "loop".to_string();
+        found.file_line = 7;
+
+        let mut diagnostic = Diagnostic::at(codes::SYNTAX, "Syntax error", "Assembler", &found, vec![TokenType::Instruction, TokenType::Directive]);
+        assert_eq!(diagnostic.line, 7);
+        assert_eq!(diagnostic.to_string(), "7: error[E0001]: Syntax error, expected: [Instruction, Directive], recieved: Token{value:loop, type:Label}");
+
+        diagnostic.file = Some("game.asm".to_string());
+        assert!(diagnostic.to_string().starts_with("game.asm:7: error[E0001]"));
+    }
+
+    #[test]
+    fn notes_hang_off_the_error()
+    {
+        let diagnostic = Diagnostic::new(codes::DUPLICATE_LABEL, "Label is already defined", "Assembler")
+            .with_note(Diagnostic::note("first defined here", 3));
+
+        assert!(!diagnostic.notes[0].is_error());
+        assert_eq!(diagnostic.notes[0].code, codes::NOTE);
+        assert_eq!(diagnostic.to_string(), "error[E0005]: Label is already defined\n  3: note: first defined here");
+    }
+
+    // syntax_error
+    // an error at the given token on line 2
+    fn syntax_error(value: &str, columns: Option<(u32, u32)>) -> Diagnostic
+    {
+        let mut found = Token::empty_token();
+        found.token_type = TokenType::Label;
+        found.value = value.to_string();
+        found.file_line = 2;
+
+        let mut diagnostic = Diagnostic::at(codes::SYNTAX, "Syntax error", "Assembler", &found, vec![TokenType::Instruction]);
+        diagnostic.file = Some("game.asm".to_string());
+        diagnostic.columns = columns;
+        diagnostic
+    }
+
+    #[test]
+    fn render_puts_a_caret_under_the_token()
+    {
+        let source = ".ORG $0600\n  LDA oops ; load it\n";
+
+        assert_eq!(syntax_error("oops", Some((7, 11))).render(Some(source), false), concat!(
+            "error[E0001]: Syntax error\n",
+            " --> game.asm:2:7\n",
+            "  |\n",
+            "2 |   LDA oops ; load it\n",
+            "  |       ^^^^ expected an instruction, found `oops`\n"));
+    }
+
+    #[test]
+    fn render_guesses_the_columns()
+    {
+        let source = ".ORG $0600\n  LDA oops\n";
+        assert!(syntax_error("oops", None).render(Some(source), false).contains("2 |   LDA oops\n  |       ^^^^ expected"));
+    }
+
+    #[test]
+    fn render_keeps_tabs_so_the_caret_lines_up()
+    {
+        let source = ".ORG $0600\n\tLDA\toops\n";
+        assert!(syntax_error("oops", None).render(Some(source), false).contains("2 | \tLDA\toops\n  | \t   \t^^^^ expected"));
+    }
+
+    #[test]
+    fn render_shows_each_line_a_note_is_about()
+    {
+        let source = "start:\n  NOP\nstart:\n";
+        let mut diagnostic = Diagnostic::new(codes::DUPLICATE_LABEL, "Label is already defined", "Assembler")
+            .with_note(Diagnostic::note("first defined here", 1));
+        diagnostic.line = 3;
+        diagnostic.columns = Some((1, 6));
+        diagnostic.notes[0].columns = Some((1, 6));
+
+        assert_eq!(diagnostic.render(Some(source), false), concat!(
+            "error[E0005]: Label is already defined\n",
+            "  |\n",
+            "3 | start:\n",
+            "  | ^^^^^\n",
+            "note: first defined here\n",
+            "  |\n",
+            "1 | start:\n",
+            "  | ^^^^^\n"));
+    }
+
+    #[test]
+    fn render_without_the_source()
+    {
+        assert_eq!(syntax_error("oops", None).render(None, false), concat!(
+            "error[E0001]: Syntax error\n",
+            " --> game.asm:2\n",
+            "  expected an instruction\n"));
+    }
+
+    #[test]
+    fn render_in_color()
+    {
+        let text = syntax_error("oops", Some((7, 11))).render(Some(".ORG $0600\n  LDA oops\n"), true);
+        assert!(text.starts_with("\x1b[1;31merror[E0001]\x1b[0m\x1b[1m: Syntax error\x1b[0m\n"));
+        assert!(text.contains("\x1b[1;31m^^^^\x1b[0m"));
+    }
+}
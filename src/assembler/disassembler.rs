@@ -0,0 +1,572 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use crate::assembler::cpu::Cpu;
+use crate::assembler::instruction::{AddressingMode, Instruction, Opcode};
+
+
+// DisassemblerOptions
+// what the bytes are and where they sit,
+// set from the cli or by whoever is using the library
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+pub struct DisassemblerOptions
+{
+    pub cpu: Cpu,
+    pub origin: u32,                    // the address of the first byte
+    pub extra_opcodes: Vec<Opcode>,     // rows of an instruction table on top of the cpu's
+    pub symbols: Vec<(String, u32)>,    // names put in place of the addresses they hold
+    pub data_ranges: Vec<(u32, u32)>,   // addresses (inclusive) that are data and not code
+    pub trace: bool,                    // disassemble_file traces the code before disassembling
+    pub entry_points: Vec<u32>,         // where tracing starts, the vectors if there are none
+}
+
+
+// OperandKind
+// what an operand's number means, only
+// addresses get a symbol put in their place
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum OperandKind
+{
+    Value,      // an immediate, a bank or a length
+    Address,    // memory the instruction reads or writes
+    Target,     // where a branch goes, worked out from the offset
+}
+
+// Operand
+// one number of an instruction, size is
+// how many bytes it is written with
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Operand
+{
+    pub value: u32,
+    pub size: u32,
+    pub kind: OperandKind,
+}
+
+// LineKind
+// an instruction or bytes that
+// couldn't be or weren't to be read as one
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub enum LineKind
+{
+    Instruction { mnemonic: String, mode: AddressingMode, operands: Vec<Operand> },
+    Data,
+}
+
+// DisassembledLine
+// the bytes at an address and what they are
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct DisassembledLine
+{
+    pub address: u32,
+    pub bytes: Vec<u8>,
+    pub kind: LineKind,
+}
+
+
+// the most bytes put on one .BYTE line
+const BYTES_PER_LINE: usize = 8;
+
+
+// disassemble
+// reads the bytes from the start as instructions, bytes that
+// aren't an opcode of the cpu, run off the end or are in a
+// data range become data, data stops at a symbol so it can be labelled
+pub fn disassemble(bytes: &[u8], options: &DisassemblerOptions) -> Vec<DisassembledLine>
+{
+    let mut decoder = Decoder::new(options.cpu, &options.extra_opcodes);
+    let mut lines: Vec<DisassembledLine> = vec![];
+
+    let mut offset = 0;
+    while offset < bytes.len()
+    {
+        let address = options.origin + offset as u32;
+
+        let is_data = options.data_ranges.iter().any(|(start, end)| address >= *start && address <= *end);
+
+        // an instruction can't run into a data range
+        let code_end = options.data_ranges.iter()
+            .filter(|(start, _)| *start > address)
+            .map(|(start, _)| (start - options.origin) as usize)
+            .fold(bytes.len(), usize::min);
+
+        if !is_data
+        {
+            if let Some(line) = decoder.decode(&bytes[..code_end], offset, address)
+            {
+                offset += line.bytes.len();
+                lines.push(line);
+                continue;
+            }
+        }
+
+        // add to the data line before this one if it's data
+        // and has the room, a symbol or data range starts a new one
+        let labelled = options.symbols.iter().any(|(_, value)| *value == address)
+            || options.data_ranges.iter().any(|(start, _)| *start == address);
+        match lines.last_mut()
+        {
+            Some(last) if last.kind == LineKind::Data && last.bytes.len() < BYTES_PER_LINE && !labelled =>
+            {
+                last.bytes.push(bytes[offset]);
+            }
+            _ => lines.push(DisassembledLine { address, bytes: vec![bytes[offset]], kind: LineKind::Data }),
+        }
+        offset += 1;
+    }
+
+    lines
+}
+
+// trace
+// follows the code from the entry points through every branch,
+// jump and call it can, the options that come back have the bytes
+// that weren't reached as data ranges and labels for every entry
+// point and target, symbols already given for an address win
+pub fn trace(bytes: &[u8], options: &DisassemblerOptions) -> DisassemblerOptions
+{
+    let mut traced = options.clone();
+    let end = options.origin + bytes.len() as u32;
+    let in_image = |address: u32| address >= options.origin && address < end;
+
+    // without entry points the nmi, reset and irq
+    // vectors are read if the bytes cover them
+    let mut entry_points: Vec<(String, u32)> = options.entry_points.iter().map(|a| (format!("L{:04X}", a), *a)).collect();
+    if entry_points.is_empty() && options.origin <= 0xfffa && end >= 0x10000
+    {
+        for (i, name) in ["nmi", "reset", "irq"].iter().enumerate()
+        {
+            let offset = (0xfffa - options.origin) as usize + i * 2;
+            entry_points.push((name.to_string(), bytes[offset] as u32 | (bytes[offset + 1] as u32) << 8));
+        }
+        traced.data_ranges.push((0xfffa, 0xffff));
+    }
+
+    let mut labels: Vec<(String, u32)> = entry_points.iter().filter(|(_, a)| in_image(*a)).cloned().collect();
+    let mut code = vec![false; bytes.len()];
+    let mut visited: HashSet<u32> = HashSet::new();
+
+    // each path keeps the register widths
+    // it had when it got to the address
+    let mut decoder = Decoder::new(options.cpu, &options.extra_opcodes);
+    let mut paths: Vec<(u32, bool, bool)> = entry_points.iter().map(|(_, a)| (*a, false, false)).collect();
+
+    while let Some((address, accumulator_16, index_16)) = paths.pop()
+    {
+        if !in_image(address) || !visited.insert(address)
+        {
+            continue;
+        }
+
+        decoder.accumulator_16 = accumulator_16;
+        decoder.index_16 = index_16;
+
+        let offset = (address - options.origin) as usize;
+        let line = match decoder.decode(bytes, offset, address)
+        {
+            Some(line) => line,
+            None => continue,
+        };
+
+        // bytes some other path read as a different
+        // instruction, this path has gone wrong
+        let span = offset..offset + line.bytes.len();
+        if code[span.clone()].iter().any(|c| *c)
+        {
+            continue;
+        }
+        code[span].iter_mut().for_each(|c| *c = true);
+
+        let (target, carries_on) = match &line.kind
+        {
+            LineKind::Instruction { mnemonic, mode, operands } => flow(mnemonic, *mode, operands),
+            LineKind::Data => (None, false),
+        };
+
+        if let Some(target) = target
+        {
+            if in_image(target) && !labels.iter().any(|(_, a)| *a == target)
+            {
+                labels.push((format!("L{:04X}", target), target));
+            }
+            paths.push((target, decoder.accumulator_16, decoder.index_16));
+        }
+        if carries_on
+        {
+            paths.push((address + line.bytes.len() as u32, decoder.accumulator_16, decoder.index_16));
+        }
+    }
+
+    // every run of bytes nothing reached is data
+    let mut offset = 0;
+    while offset < code.len()
+    {
+        let start = offset;
+        while offset < code.len() && !code[offset]
+        {
+            offset += 1;
+        }
+        if offset > start
+        {
+            traced.data_ranges.push((options.origin + start as u32, options.origin + offset as u32 - 1));
+        }
+        offset += 1;
+    }
+
+    labels.sort_by_key(|(_, a)| *a);
+    for (name, address) in labels
+    {
+        if !traced.symbols.iter().any(|(_, a)| *a == address)
+        {
+            traced.symbols.push((name, address));
+        }
+    }
+
+    traced
+}
+
+// flow
+// where the cpu can go after an instruction, the target
+// of its jump or branch and if it carries on to the next one
+fn flow(mnemonic: &str, mode: AddressingMode, operands: &[Operand]) -> (Option<u32>, bool)
+{
+    use AddressingMode::*;
+    let target = operands.last().map(|operand| operand.value);
+
+    match mnemonic
+    {
+        "rts" | "rti" | "rtl" | "brk" | "stp" | "jam" => (None, false),
+        "per" => (None, true),
+        "jmp" | "jml" if matches!(mode, Absolute | AbsoluteLong) => (target, false),
+        "jmp" | "jml" => (None, false),
+        "jsr" | "jsl" if matches!(mode, Absolute | AbsoluteLong) => (target, true),
+        "bra" | "brl" => (target, false),
+        _ if matches!(mode, Relative | LongRelative | ZeroPageRelative) => (target, true),
+        _ => (None, true),
+    }
+}
+
+// disassembly_source
+// writes the lines as source this assembler takes back, forward
+// references aren't allowed so every symbol is set with = at the
+// top and shows up as a comment where it points in the code
+pub fn disassembly_source(lines: &[DisassembledLine], options: &DisassemblerOptions) -> String
+{
+    let mut out = String::new();
+
+    if options.cpu != Cpu::Mos6502
+    {
+        out += &format!(".CPU {}\n\n", options.cpu);
+    }
+
+    // the first name given for an address is the one used
+    let mut names: HashMap<u32, &str> = HashMap::new();
+    for (name, value) in &options.symbols
+    {
+        if !names.contains_key(value)
+        {
+            names.insert(*value, name);
+            out += &format!("{} = {}\n", name, number(*value, value_size(*value)));
+        }
+    }
+    if !names.is_empty()
+    {
+        out += "\n";
+    }
+
+    let mut address = None;
+    for line in lines
+    {
+        // a new .ORG when the lines jump
+        if address != Some(line.address)
+        {
+            out += &format!(".ORG {}\n", number(line.address, value_size(line.address).max(2)));
+        }
+        address = Some(line.address + line.bytes.len() as u32);
+
+        for (name, value) in &options.symbols
+        {
+            if *value == line.address
+            {
+                out += &format!("; {}\n", name);
+            }
+        }
+
+        let text = match &line.kind
+        {
+            LineKind::Instruction { mnemonic, mode, operands } => instruction_text(mnemonic, *mode, operands, &names),
+            LineKind::Data =>
+            {
+                let bytes: Vec<String> = line.bytes.iter().map(|b| format!("${:02X}", b)).collect();
+                format!(".BYTE {}", bytes.join(", "))
+            }
+        };
+
+        let bytes: Vec<String> = line.bytes.iter().map(|b| format!("{:02X}", b)).collect();
+        out += &format!("    {:<24}; {}  {}\n", text, number(line.address, value_size(line.address).max(2)), bytes.join(" "));
+
+        // the assembler has to be told the
+        // register widths the code switched to
+        if let LineKind::Instruction { mnemonic, operands, .. } = &line.kind
+        {
+            if options.cpu.is_65816() && (mnemonic == "rep" || mnemonic == "sep")
+            {
+                let (accumulator, index) = if mnemonic == "rep" { (".A16", ".I16") } else { (".A8", ".I8") };
+                if operands[0].value & 0x20 != 0
+                {
+                    out += &format!("    {}\n", accumulator);
+                }
+                if operands[0].value & 0x10 != 0
+                {
+                    out += &format!("    {}\n", index);
+                }
+            }
+        }
+    }
+
+    out
+}
+
+
+// Decoder
+// reads instructions one at a time, on the 65816 it
+// follows REP and SEP to know how wide immediates are
+struct Decoder
+{
+    opcodes: HashMap<u8, Opcode>,
+    is_65816: bool,
+    accumulator_16: bool,
+    index_16: bool,
+}
+
+impl Decoder
+{
+    // new
+    // an opcode goes to the first row
+    // of the cpu's instructions that has it
+    fn new(cpu: Cpu, extra: &[Opcode]) -> Decoder
+    {
+        let mut opcodes: HashMap<u8, Opcode> = HashMap::new();
+        for opcode in Instruction::opcodes(cpu, extra)
+        {
+            opcodes.entry(opcode.opcode).or_insert(opcode);
+        }
+
+        Decoder { opcodes, is_65816: cpu.is_65816(), accumulator_16: false, index_16: false }
+    }
+
+    // decode
+    // the instruction at offset, none if the byte
+    // isn't an opcode or its operand runs off the end
+    fn decode(&mut self, bytes: &[u8], offset: usize, address: u32) -> Option<DisassembledLine>
+    {
+        let opcode = self.opcodes.get(&bytes[offset])?;
+
+        let mut size = opcode.bytes as usize;
+        if self.is_65816 && opcode.is_wide(self.accumulator_16, self.index_16)
+        {
+            size += 1;
+        }
+        if offset + size > bytes.len()
+        {
+            return None;
+        }
+
+        let bytes = &bytes[offset..offset + size];
+
+        // the little endian number at bytes[start..start+size]
+        let read = |start: usize, size: usize| -> u32
+        {
+            bytes[start..start + size].iter().rev().fold(0, |n, b| (n << 8) | *b as u32)
+        };
+
+        // branches stay in the bank they are in
+        let target = |next: u32, offset: i32| -> u32
+        {
+            (address & !0xffff) | (next.wrapping_add(offset as u32) & 0xffff)
+        };
+
+        let operand = |value: u32, size: u32, kind: OperandKind| Operand { value, size, kind };
+
+        use AddressingMode::*;
+        use OperandKind::*;
+        let operands = match opcode.mode
+        {
+            Implied | Accumulator => vec![],
+            Immediate => vec![operand(read(1, size - 1), size as u32 - 1, Value)],
+            Relative => vec![operand(target(address + 2, bytes[1] as i8 as i32), 2, Target)],
+            LongRelative => vec![operand(target(address + 3, read(1, 2) as u16 as i16 as i32), 2, Target)],
+            ZeroPageRelative => vec![operand(bytes[1] as u32, 1, Address), operand(target(address + 3, bytes[2] as i8 as i32), 2, Target)],
+            // written source bank first but
+            // stored destination bank first
+            BlockMove => vec![operand(bytes[2] as u32, 1, Value), operand(bytes[1] as u32, 1, Value)],
+            BlockTransfer => vec![operand(read(1, 2), 2, Address), operand(read(3, 2), 2, Address), operand(read(5, 2), 2, Value)],
+            TestZeroPage | TestZeroPageX => vec![operand(bytes[1] as u32, 1, Value), operand(bytes[2] as u32, 1, Address)],
+            TestAbsolute | TestAbsoluteX => vec![operand(bytes[1] as u32, 1, Value), operand(read(2, 2), 2, Address)],
+            _ => vec![operand(read(1, size - 1), size as u32 - 1, Address)],
+        };
+
+        if self.is_65816 && (opcode.mnemonic == "rep" || opcode.mnemonic == "sep")
+        {
+            let set = opcode.mnemonic == "sep";
+            if bytes[1] & 0x20 != 0
+            {
+                self.accumulator_16 = !set;
+            }
+            if bytes[1] & 0x10 != 0
+            {
+                self.index_16 = !set;
+            }
+        }
+
+        Some(DisassembledLine
+        {
+            address,
+            bytes: bytes.to_vec(),
+            kind: LineKind::Instruction { mnemonic: opcode.mnemonic.clone(), mode: opcode.mode, operands },
+        })
+    }
+}
+
+
+// instruction_text
+// fills in the mode's syntax with the operands, an address
+// becomes a symbol only if the symbol reads back as the
+// same size so the same addressing mode is picked
+fn instruction_text(mnemonic: &str, mode: AddressingMode, operands: &[Operand], names: &HashMap<u32, &str>) -> String
+{
+    let mut operands = operands.iter().map(|operand|
+    {
+        let size = value_size(operand.value);
+        let fits = match operand.kind
+        {
+            OperandKind::Value => false,
+            OperandKind::Address => size == operand.size,
+            OperandKind::Target => size <= 2,
+        };
+
+        match names.get(&operand.value)
+        {
+            Some(name) if fits => name.to_string(),
+            _ => number(operand.value, operand.size),
+        }
+    });
+
+    let syntax = mode.syntax();
+    let mut text = String::new();
+    let mut i = 0;
+    while i < syntax.len()
+    {
+        let rest = &syntax[i..];
+        match ["$nnnnnn", "$nnnn", "$nn", "label"].iter().find(|p| rest.starts_with(*p))
+        {
+            Some(placeholder) =>
+            {
+                text += &operands.next().unwrap_or_default();
+                i += placeholder.len();
+            }
+            None =>
+            {
+                text += &rest[..1];
+                i += 1;
+            }
+        }
+    }
+
+    format!("{} {}", mnemonic.to_uppercase(), text).trim_end().to_string()
+}
+
+// number
+// hex with the digits the lexer
+// needs to read it back as size bytes
+fn number(value: u32, size: u32) -> String
+{
+    match size
+    {
+        1 => format!("${:02X}", value),
+        2 => format!("${:04X}", value),
+        _ => format!("${:06X}", value),
+    }
+}
+
+// value_size
+// the bytes a symbol set to the value takes
+fn value_size(value: u32) -> u32
+{
+    if value <= 0xff
+    {
+        1
+    }
+    else if value <= 0xffff
+    {
+        2
+    }
+    else
+    {
+        3
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::assembler::options::AssemblerOptions;
+
+    // rom
+    // a page at $FF00 with a reset routine, a
+    // subroutine, an RTI, data and the vectors
+    fn rom() -> Vec<u8>
+    {
+        let mut bytes = vec![
+            0xa2, 0xff,         // reset: LDX #$FF
+            0x9a,               //        TXS
+            0xa5, 0x10,         // loop:  LDA $10
+            0xd0, 0xfc,         //        BNE loop
+            0x20, 0x0d, 0xff,   //        JSR sub
+            0x4c, 0x03, 0xff,   //        JMP loop
+            0xe6, 0x10,         // sub:   INC $10
+            0x60,               //        RTS
+            0x40,               // nmi:   RTI
+        ];
+        while bytes.len() < 0xfa
+        {
+            bytes.push((bytes.len() * 37) as u8);
+        }
+        bytes.extend([0x10, 0xff, 0x00, 0xff, 0x10, 0xff]);
+        bytes
+    }
+
+    #[test]
+    fn traced_rom_assembles_back_to_the_same_bytes()
+    {
+        let bytes = rom();
+        let options = trace(&bytes, &DisassemblerOptions { origin: 0xff00, ..Default::default() });
+        let source = disassembly_source(&disassemble(&bytes, &options), &options);
+
+        let dir = std::env::temp_dir();
+        let input = dir.join(format!("asm_6502_round_trip_{}.asm", std::process::id()));
+        let output = dir.join(format!("asm_6502_round_trip_{}.bin", std::process::id()));
+        std::fs::write(&input, &source).unwrap();
+
+        let result = crate::assemble_file(input.to_str().unwrap(), output.to_str().unwrap(), AssemblerOptions::default());
+        let assembled = std::fs::read(&output);
+        std::fs::remove_file(&input).ok();
+        std::fs::remove_file(&output).ok();
+
+        assert!(result.is_ok(), "{:?}\n{}", result, source);
+        assert_eq!(assembled.unwrap(), bytes, "{}", source);
+        assert!(source.contains("JSR L"), "{}", source);
+    }
+}
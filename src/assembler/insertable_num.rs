@@ -13,6 +13,7 @@ pub enum InsertableNum
 {
     Byte(u8),
     TwoByte(u16),
+    ThreeByte(u32),     // a 65816 long address, only the bottom 3 bytes are used
 }
 
 impl InsertableNum
@@ -25,44 +26,54 @@ impl InsertableNum
         match self
         {
             InsertableNum::Byte(num) => num as u32,
-            InsertableNum::TwoByte(num) => num as u32
+            InsertableNum::TwoByte(num) => num as u32,
+            InsertableNum::ThreeByte(num) => num,
         }
     }
 
-    // unwrap_byte
-    // returns a u8
-    // will not cast this into u16s because we don't believe
-    // in down casting
-    pub fn unwrap_byte(self) -> u8
+    // sized
+    // a number of the given size in bytes
+    // with anything that doesn't fit cut off
+    pub fn sized(num: u32, size: u32) -> InsertableNum
     {
-        match self
+        match size
         {
-            InsertableNum::Byte(num) => num as u8,
-            InsertableNum::TwoByte(_) => panic!("Down casting is frowned upon my dude")
+            1 => InsertableNum::Byte(num as u8),
+            2 => InsertableNum::TwoByte(num as u16),
+            _ => InsertableNum::ThreeByte(num & 0xffffff),
         }
     }
 
-    // unwrap_twobyte 
-    // exptects it be two bytes
-    // never panics because we believe in upcasting
-    pub fn unwrap_twobyte(self) -> u16
+    // size
+    // how many bytes it takes up
+    pub fn size(self) -> u32
     {
         match self
         {
-            InsertableNum::TwoByte(num) => num as u16,
-            InsertableNum::Byte(num) => num as u16
+            InsertableNum::Byte(_) => 1,
+            InsertableNum::TwoByte(_) => 2,
+            InsertableNum::ThreeByte(_) => 3,
         }
     }
 
+    // to_bytes
+    // the number little endian
+    pub fn to_bytes(self) -> Vec<u8>
+    {
+        let num = self.unwrap();
+        (0..self.size()).map(|i| (num >> (8 * i)) as u8).collect()
+    }
 
-    // is_two_bytes 
-    // returns true if this number is twobytes 
-    pub fn is_two_bytes(self) -> bool 
+    // unwrap_byte
+    // returns a u8
+    // will not cast this into u16s because we don't believe
+    // in down casting
+    pub fn unwrap_byte(self) -> u8
     {
         match self
         {
-            InsertableNum::TwoByte(_) => true,
-            InsertableNum::Byte(_) => false
+            InsertableNum::Byte(num) => num as u8,
+            InsertableNum::TwoByte(_) | InsertableNum::ThreeByte(_) => panic!("Down casting is frowned upon my dude")
         }
     }
 }
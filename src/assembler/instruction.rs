@@ -1,11 +1,15 @@
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::OnceLock;
 use crate::assembler::lexical_analyzer::TokenType;
+use crate::assembler::cpu::Cpu;
+use crate::assembler::diagnostic::{Diagnostic, codes};
 
 
 
-// possible grammars that an instrution 
-// could be 
+// possible grammars that an instrution
+// could be
 mod grammars{
     use crate::assembler::lexical_analyzer::TokenType;
     use crate::assembler::lexical_analyzer::TokenType::*;
@@ -15,622 +19,688 @@ mod grammars{
     pub const ZEROPAGEX:    [TokenType; 4] = [Num1Bytes, Comma, RegX, EOL];
     pub const ZEROPAGEY:    [TokenType; 4] = [Num1Bytes, Comma, RegY, EOL];
     pub const ABSOLUTE:     [TokenType; 2] = [Num2Bytes, EOL];
-    pub const ABSOLUTEX:    [TokenType; 4] = [Num2Bytes, Comma, RegX, EOL]; 
-    pub const ABSOLUTEY:    [TokenType; 4] = [Num2Bytes, Comma, RegY, EOL]; 
+    pub const ABSOLUTEX:    [TokenType; 4] = [Num2Bytes, Comma, RegX, EOL];
+    pub const ABSOLUTEY:    [TokenType; 4] = [Num2Bytes, Comma, RegY, EOL];
     pub const INDIRECT:     [TokenType; 4] = [LeftParenth, Num2Bytes, RightParenth, EOL];
-    pub const INDIRECTX:    [TokenType; 6] = [LeftParenth, Num1Bytes, Comma, RegX, RightParenth, EOL];  
-    pub const INDIRECTY:    [TokenType; 6] = [LeftParenth, Num1Bytes, RightParenth, Comma, RegY, EOL];  
-    pub const ACCUMULATOR:  [TokenType; 2] = [RegA, EOL]; 
+    pub const INDIRECTX:    [TokenType; 6] = [LeftParenth, Num1Bytes, Comma, RegX, RightParenth, EOL];
+    pub const INDIRECTY:    [TokenType; 6] = [LeftParenth, Num1Bytes, RightParenth, Comma, RegY, EOL];
+    pub const ACCUMULATOR:  [TokenType; 2] = [RegA, EOL];
+    pub const ZEROPAGEINDIRECT: [TokenType; 4] = [LeftParenth, Num1Bytes, RightParenth, EOL];
+    pub const INDIRECTABSOLUTEX: [TokenType; 6] = [LeftParenth, Num2Bytes, Comma, RegX, RightParenth, EOL];
+    pub const ZEROPAGERELATIVE: [TokenType; 4] = [Num1Bytes, Comma, Num2Bytes, EOL];
+
+    // 65816 modes
+    pub const IMMEDIAT2BYTE:[TokenType; 3] = [Hash, Num2Bytes, EOL];
+    pub const ABSOLUTELONG: [TokenType; 2] = [Num3Bytes, EOL];
+    pub const ABSOLUTELONGX:[TokenType; 4] = [Num3Bytes, Comma, RegX, EOL];
+    pub const INDIRECTLONG: [TokenType; 4] = [LeftBracket, Num2Bytes, RightBracket, EOL];
+    pub const DIRECTINDIRECTLONG: [TokenType; 4] = [LeftBracket, Num1Bytes, RightBracket, EOL];
+    pub const DIRECTINDIRECTLONGY: [TokenType; 6] = [LeftBracket, Num1Bytes, RightBracket, Comma, RegY, EOL];
+    pub const STACKRELATIVE: [TokenType; 4] = [Num1Bytes, Comma, RegS, EOL];
+    pub const STACKRELATIVEINDIRECTY: [TokenType; 8] = [LeftParenth, Num1Bytes, Comma, RegS, RightParenth, Comma, RegY, EOL];
+    pub const BLOCKMOVE:    [TokenType; 4] = [Num1Bytes, Comma, Num1Bytes, EOL];
+
+    // HuC6280 modes
+    pub const BLOCKTRANSFER:[TokenType; 6] = [Num2Bytes, Comma, Num2Bytes, Comma, Num2Bytes, EOL];
+    pub const TESTZEROPAGE: [TokenType; 5] = [Hash, Num1Bytes, Comma, Num1Bytes, EOL];
+    pub const TESTZEROPAGEX:[TokenType; 7] = [Hash, Num1Bytes, Comma, Num1Bytes, Comma, RegX, EOL];
+    pub const TESTABSOLUTE: [TokenType; 5] = [Hash, Num1Bytes, Comma, Num2Bytes, EOL];
+    pub const TESTABSOLUTEX:[TokenType; 7] = [Hash, Num1Bytes, Comma, Num2Bytes, Comma, RegX, EOL];
 
     pub const EMPTY:        [TokenType; 1] = [EOL];
 }
 
-// instruction 
-// holds a sring code i.e. "str", "and", etc.
-// holds a hashmap full of possible grammars that this could be 
+// the instructions whose immediate follows the width
+// of the accumulator or the index registers on the 65816
+const ACCUMULATOR_WIDTH: [&str; 8] = ["adc", "and", "bit", "cmp", "eor", "lda", "ora", "sbc"];
+const INDEX_WIDTH: [&str; 4] = ["cpx", "cpy", "ldx", "ldy"];
+
+// the instruction table every cpu is built from
+const BUILTIN_TABLE: &str = include_str!("instructions.txt");
+
+
+// AddressingMode
+// the ways an instruction can be given
+// its operand, with the names used in
+// instruction tables
 #[derive(Debug)]
 #[derive(Clone)]
+#[derive(Copy)]
 #[derive(PartialEq)]
-pub struct Instruction 
+#[derive(Eq)]
+#[derive(Hash)]
+pub enum AddressingMode
 {
-    pub string_code: String,
-    pub opcode_grammer: Vec<(u8, Vec<TokenType>)>
+    Implied,                // CLC
+    Accumulator,            // ASL A
+    Immediate,              // LDA #$10
+    ZeroPage,               // LDA $10
+    ZeroPageX,              // LDA $10,X
+    ZeroPageY,              // LDX $10,Y
+    Absolute,               // LDA $1234
+    AbsoluteX,              // LDA $1234,X
+    AbsoluteY,              // LDA $1234,Y
+    Indirect,               // JMP ($1234)
+    IndirectX,              // LDA ($10,X)
+    IndirectY,              // LDA ($10),Y
+    ZeroPageIndirect,       // LDA ($10)
+    IndirectAbsoluteX,      // JMP ($1234,X)
+    Relative,               // BNE label
+    ZeroPageRelative,       // BBR0 $10,label
+    AbsoluteLong,           // LDA $123456
+    AbsoluteLongX,          // LDA $123456,X
+    IndirectLong,           // JML [$1234]
+    DirectIndirectLong,     // LDA [$10]
+    DirectIndirectLongY,    // LDA [$10],Y
+    StackRelative,          // LDA $03,S
+    StackRelativeIndirectY, // LDA ($03,S),Y
+    BlockMove,              // MVN $01,$02
+    LongRelative,           // BRL label
+    BlockTransfer,          // TII $2000,$3000,$0100
+    TestZeroPage,           // TST #$01,$10
+    TestZeroPageX,          // TST #$01,$10,X
+    TestAbsolute,           // TST #$01,$1234
+    TestAbsoluteX,          // TST #$01,$1234,X
 }
 
-
-
-impl Instruction 
+impl AddressingMode
 {
-    // get_map
-    // returns a list 
-    // returns a map of instruction string codes 
-    // paired with their instruction struct  
-    pub fn get_map() -> HashMap<String,Instruction>
+    // all
+    // every addressing mode there is
+    pub fn all() -> Vec<AddressingMode>
     {
-        let mut map: HashMap<String,Instruction> = HashMap::new();
-        // adc
-        map.insert("adc".to_string(), Instruction{
-            string_code:"adc".to_string(),
-            opcode_grammer: vec![
-                (0x61,  grammars::INDIRECTX.to_vec()),
-                (0x71,  grammars::INDIRECTY.to_vec()),
-                (0x65,  grammars::ZEROPAGE.to_vec()),
-                (0x79,  grammars::ABSOLUTEY.to_vec()),
-                (0x69,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0x75,  grammars::ZEROPAGEX.to_vec()),
-                (0x6d,  grammars::ABSOLUTE.to_vec()),
-                (0x7d,  grammars::ABSOLUTEX.to_vec()),
-            ],
-        });
-
-
-        // and
-        map.insert("and".to_string(), Instruction{
-            string_code:"and".to_string(),
-            opcode_grammer: vec![
-                (0x21,  grammars::INDIRECTX.to_vec()),
-                (0x31,  grammars::INDIRECTY.to_vec()),
-                (0x29,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0x25,  grammars::ZEROPAGE.to_vec()),
-                (0x35,  grammars::ZEROPAGEX.to_vec()),
-                (0x2d,  grammars::ABSOLUTE.to_vec()),
-                (0x3d,  grammars::ABSOLUTEX.to_vec()),
-                (0x39,  grammars::ABSOLUTEY.to_vec()),
-            ],
-        });
-
-
-        // asl 
-        map.insert("asl".to_string(), Instruction{
-            string_code:"asl".to_string(),
-            opcode_grammer: vec![
-                (0x0a,  grammars::ACCUMULATOR.to_vec()),
-                (0x06,  grammars::ZEROPAGE.to_vec()),
-                (0x16,  grammars::ZEROPAGEX.to_vec()),
-                (0x0e,  grammars::ABSOLUTE.to_vec()),
-                (0x1e,  grammars::ABSOLUTEX.to_vec()),
-            ],
-        });
-
-
-        // Bit
-        map.insert("bit".to_string(), Instruction{
-            string_code:"bit".to_string(),
-            opcode_grammer: vec![
-                (0x24,  grammars::ZEROPAGE.to_vec()),
-                (0x2c,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-        // bpl
-        map.insert("bpl".to_string(), Instruction{
-            string_code:"bpl".to_string(),
-            opcode_grammer: vec![
-                (0x10,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-        // bmi
-        map.insert("bmi".to_string(), Instruction{
-            string_code:"bmi".to_string(),
-            opcode_grammer: vec![
-                (0x30,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-        // bvc
-        map.insert("bvc".to_string(), Instruction{
-            string_code:"bvc".to_string(),
-            opcode_grammer: vec![
-                (0x50,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-        // bvs
-        map.insert("bvs".to_string(), Instruction{
-            string_code:"bvs".to_string(),
-            opcode_grammer: vec![
-                (0x70,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-        // bcc
-        map.insert("bcc".to_string(), Instruction{
-            string_code:"bcc".to_string(),
-            opcode_grammer: vec![
-                (0x90,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-        // bcs
-        map.insert("bcs".to_string(), Instruction{
-            string_code:"bcs".to_string(),
-            opcode_grammer: vec![
-                (0xb0,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-        // bne
-        map.insert("bne".to_string(), Instruction{
-            string_code:"bne".to_string(),
-            opcode_grammer: vec![
-                (0xd0,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-        // beq
-        map.insert("beq".to_string(), Instruction{
-            string_code:"beq".to_string(),
-            opcode_grammer: vec![
-                (0xf0,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-
-
-        // brk
-        map.insert("brk".to_string(), Instruction{
-            string_code:"brk".to_string(),
-            opcode_grammer: vec![
-                (0x00, grammars::EMPTY.to_vec())
-            ],
-        });
-
-
-
-         // cmp
-         map.insert("cmp".to_string(), Instruction{
-            string_code:"cmp".to_string(),
-            opcode_grammer: vec![
-                (0xc1,  grammars::INDIRECTX.to_vec()),
-                (0xd1,  grammars::INDIRECTY.to_vec()),
-                (0xc9,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0xc5,  grammars::ZEROPAGE.to_vec()),
-                (0xd5,  grammars::ZEROPAGEX.to_vec()),
-                (0xcd,  grammars::ABSOLUTE.to_vec()),
-                (0xdd,  grammars::ABSOLUTEX.to_vec()),
-                (0xd9,  grammars::ABSOLUTEY.to_vec()),
-            ],
-        });
-
-
-        // cpx
-        map.insert("cpx".to_string(), Instruction{
-            string_code:"cpx".to_string(),
-            opcode_grammer: vec![
-                (0xe0,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0xe4,  grammars::ZEROPAGE.to_vec()),
-                (0xec,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-
-        // cpy
-        map.insert("cpy".to_string(), Instruction{
-            string_code:"cpy".to_string(),
-            opcode_grammer: vec![
-                (0xc0,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0xc4,  grammars::ZEROPAGE.to_vec()),
-                (0xcc,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-
-
-
-        // dec
-        map.insert("dec".to_string(), Instruction{
-            string_code:"dec".to_string(),
-            opcode_grammer: vec![
-                (0xc6,  grammars::ZEROPAGE.to_vec()),
-                (0xd6,  grammars::ZEROPAGEX.to_vec()),
-                (0xce,  grammars::ABSOLUTE.to_vec()),
-                (0xde,  grammars::ABSOLUTEX.to_vec()),
-            ],
-        });
-
-
-        // eor
-        map.insert("eor".to_string(), Instruction{
-            string_code:"eor".to_string(),
-            opcode_grammer: vec![
-                (0x41,  grammars::INDIRECTX.to_vec()),
-                (0x51,  grammars::INDIRECTY.to_vec()),
-                (0x49,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0x45,  grammars::ZEROPAGE.to_vec()),
-                (0x55,  grammars::ZEROPAGEX.to_vec()),
-                (0x4d,  grammars::ABSOLUTE.to_vec()),
-                (0x5d,  grammars::ABSOLUTEX.to_vec()),
-                (0x59,  grammars::ABSOLUTEY.to_vec()),
-            ],
-        });
-
-
-        // clc
-        map.insert("clc".to_string(), Instruction{
-            string_code:"clc".to_string(),
-            opcode_grammer: vec![
-                (0x18,  grammars::EMPTY.to_vec()),
-            ],
-        });
-
-         // sec
-         map.insert("sec".to_string(), Instruction{
-            string_code:"sec".to_string(),
-            opcode_grammer: vec![
-                (0x38,  grammars::EMPTY.to_vec()),
-            ],
-        });
-
-
-         // cli
-         map.insert("cli".to_string(), Instruction{
-            string_code:"cli".to_string(),
-            opcode_grammer: vec![
-                (0x58,  grammars::EMPTY.to_vec()),
-            ],
-        });
-
-
-         // sei
-         map.insert("sei".to_string(), Instruction{
-            string_code:"sei".to_string(),
-            opcode_grammer: vec![
-                (0x78,  grammars::EMPTY.to_vec()),
-            ],
-        });
+        use AddressingMode::*;
+        vec![
+            Implied, Accumulator, Immediate, ZeroPage, ZeroPageX, ZeroPageY, Absolute, AbsoluteX, AbsoluteY,
+            Indirect, IndirectX, IndirectY, ZeroPageIndirect, IndirectAbsoluteX, Relative, ZeroPageRelative,
+            AbsoluteLong, AbsoluteLongX, IndirectLong, DirectIndirectLong, DirectIndirectLongY, StackRelative,
+            StackRelativeIndirectY, BlockMove, LongRelative, BlockTransfer, TestZeroPage, TestZeroPageX,
+            TestAbsolute, TestAbsoluteX,
+        ]
+    }
 
+    // name
+    // what it is called in an instruction table
+    pub fn name(&self) -> &'static str
+    {
+        use AddressingMode::*;
+        match self
+        {
+            Implied => "imp",
+            Accumulator => "acc",
+            Immediate => "imm",
+            ZeroPage => "zp",
+            ZeroPageX => "zpx",
+            ZeroPageY => "zpy",
+            Absolute => "abs",
+            AbsoluteX => "absx",
+            AbsoluteY => "absy",
+            Indirect => "ind",
+            IndirectX => "indx",
+            IndirectY => "indy",
+            ZeroPageIndirect => "zpind",
+            IndirectAbsoluteX => "absindx",
+            Relative => "rel",
+            ZeroPageRelative => "zprel",
+            AbsoluteLong => "long",
+            AbsoluteLongX => "longx",
+            IndirectLong => "indlong",
+            DirectIndirectLong => "dpindlong",
+            DirectIndirectLongY => "dpindlongy",
+            StackRelative => "sr",
+            StackRelativeIndirectY => "sriy",
+            BlockMove => "blockmove",
+            LongRelative => "longrel",
+            BlockTransfer => "transfer",
+            TestZeroPage => "tstzp",
+            TestZeroPageX => "tstzpx",
+            TestAbsolute => "tstabs",
+            TestAbsoluteX => "tstabsx",
+        }
+    }
 
-         // clv
-         map.insert("clv".to_string(), Instruction{
-            string_code:"clv".to_string(),
-            opcode_grammer: vec![
-                (0xb8,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // from_name
+    // the mode with the given table name
+    pub fn from_name(name: &str) -> Option<AddressingMode>
+    {
+        AddressingMode::all().into_iter().find(|m| m.name() == name.to_lowercase())
+    }
 
+    // grammar
+    // the tokens an instruction in this mode is
+    // written with, the numbers are expressions
+    pub fn grammar(&self) -> Vec<TokenType>
+    {
+        use AddressingMode::*;
+        match self
+        {
+            Implied => grammars::EMPTY.to_vec(),
+            Accumulator => grammars::ACCUMULATOR.to_vec(),
+            Immediate => grammars::IMMEDIAT1BYTE.to_vec(),
+            ZeroPage => grammars::ZEROPAGE.to_vec(),
+            ZeroPageX => grammars::ZEROPAGEX.to_vec(),
+            ZeroPageY => grammars::ZEROPAGEY.to_vec(),
+            Absolute | Relative | LongRelative => grammars::ABSOLUTE.to_vec(),
+            AbsoluteX => grammars::ABSOLUTEX.to_vec(),
+            AbsoluteY => grammars::ABSOLUTEY.to_vec(),
+            Indirect => grammars::INDIRECT.to_vec(),
+            IndirectX => grammars::INDIRECTX.to_vec(),
+            IndirectY => grammars::INDIRECTY.to_vec(),
+            ZeroPageIndirect => grammars::ZEROPAGEINDIRECT.to_vec(),
+            IndirectAbsoluteX => grammars::INDIRECTABSOLUTEX.to_vec(),
+            ZeroPageRelative => grammars::ZEROPAGERELATIVE.to_vec(),
+            AbsoluteLong => grammars::ABSOLUTELONG.to_vec(),
+            AbsoluteLongX => grammars::ABSOLUTELONGX.to_vec(),
+            IndirectLong => grammars::INDIRECTLONG.to_vec(),
+            DirectIndirectLong => grammars::DIRECTINDIRECTLONG.to_vec(),
+            DirectIndirectLongY => grammars::DIRECTINDIRECTLONGY.to_vec(),
+            StackRelative => grammars::STACKRELATIVE.to_vec(),
+            StackRelativeIndirectY => grammars::STACKRELATIVEINDIRECTY.to_vec(),
+            BlockMove => grammars::BLOCKMOVE.to_vec(),
+            BlockTransfer => grammars::BLOCKTRANSFER.to_vec(),
+            TestZeroPage => grammars::TESTZEROPAGE.to_vec(),
+            TestZeroPageX => grammars::TESTZEROPAGEX.to_vec(),
+            TestAbsolute => grammars::TESTABSOLUTE.to_vec(),
+            TestAbsoluteX => grammars::TESTABSOLUTEX.to_vec(),
+        }
+    }
 
-         // cld
-         map.insert("cld".to_string(), Instruction{
-            string_code:"cld".to_string(),
-            opcode_grammer: vec![
-                (0xd8,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // size
+    // how many bytes an instruction in
+    // this mode takes, opcode included
+    pub fn size(&self) -> u32
+    {
+        match self
+        {
+            AddressingMode::Relative => 2,
+            AddressingMode::LongRelative => 3,
+            AddressingMode::ZeroPageRelative => 3,
+            _ => 1 + self.grammar().iter().map(|t| match t
+            {
+                TokenType::Num1Bytes => 1,
+                TokenType::Num2Bytes => 2,
+                TokenType::Num3Bytes => 3,
+                _ => 0,
+            }).sum::<u32>(),
+        }
+    }
 
-         // sed
-         map.insert("sed".to_string(), Instruction{
-            string_code:"sed".to_string(),
-            opcode_grammer: vec![
-                (0xf8,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // syntax
+    // how the operand is written, nn is
+    // a byte and nnnn two, for documentation
+    pub fn syntax(&self) -> &'static str
+    {
+        use AddressingMode::*;
+        match self
+        {
+            Implied => "",
+            Accumulator => "A",
+            Immediate => "#$nn",
+            ZeroPage => "$nn",
+            ZeroPageX => "$nn,X",
+            ZeroPageY => "$nn,Y",
+            Absolute => "$nnnn",
+            AbsoluteX => "$nnnn,X",
+            AbsoluteY => "$nnnn,Y",
+            Indirect => "($nnnn)",
+            IndirectX => "($nn,X)",
+            IndirectY => "($nn),Y",
+            ZeroPageIndirect => "($nn)",
+            IndirectAbsoluteX => "($nnnn,X)",
+            Relative | LongRelative => "label",
+            ZeroPageRelative => "$nn,label",
+            AbsoluteLong => "$nnnnnn",
+            AbsoluteLongX => "$nnnnnn,X",
+            IndirectLong => "[$nnnn]",
+            DirectIndirectLong => "[$nn]",
+            DirectIndirectLongY => "[$nn],Y",
+            StackRelative => "$nn,S",
+            StackRelativeIndirectY => "($nn,S),Y",
+            BlockMove => "$nn,$nn",
+            BlockTransfer => "$nnnn,$nnnn,$nnnn",
+            TestZeroPage => "#$nn,$nn",
+            TestZeroPageX => "#$nn,$nn,X",
+            TestAbsolute => "#$nn,$nnnn",
+            TestAbsoluteX => "#$nn,$nnnn,X",
+        }
+    }
 
-        // inc
-        map.insert("inc".to_string(), Instruction{
-            string_code:"inc".to_string(),
-            opcode_grammer: vec![
-                (0xe6,  grammars::ZEROPAGE.to_vec()),
-                (0xf6,  grammars::ZEROPAGEX.to_vec()),
-                (0xee,  grammars::ABSOLUTE.to_vec()),
-                (0xfe,  grammars::ABSOLUTEX.to_vec()),
-            ],
-        });
+    // priority
+    // the order grammars are tried in, anything in
+    // brackets first since ($10) would also read as the
+    // expression $10, then the smallest operand first so
+    // zero page wins over absolute and absolute over long
+    fn priority(&self) -> (u32, u32)
+    {
+        let bracketed = matches!(self.grammar()[0], TokenType::LeftParenth | TokenType::LeftBracket);
+        (if bracketed { 0 } else { 1 }, self.size())
+    }
+}
 
 
-        
-        // jmp
-        map.insert("jmp".to_string(), Instruction{
-            string_code:"jmo".to_string(),
-            opcode_grammer: vec![
-                (0x6c,  grammars::INDIRECT.to_vec()),
-                (0x4c,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-        
-
-        // jsr
-        map.insert("jsr".to_string(), Instruction{
-            string_code:"jsr".to_string(),
-            opcode_grammer: vec![
-                (0x20,  grammars::ABSOLUTE.to_vec()),
-            ],
-        });
-        
-
-        // lda 
-        map.insert("lda".to_string(), Instruction{
-            string_code:"lda".to_string(),
-            opcode_grammer: vec![
-                (0xa1,  grammars::INDIRECTX.to_vec()),
-                (0xb1,  grammars::INDIRECTY.to_vec()),  
-                (0xa5,  grammars::ZEROPAGE.to_vec()),
-                (0xb9,  grammars::ABSOLUTEY.to_vec()),              
-                (0xb5,  grammars::ZEROPAGEX.to_vec()),
-                (0xad,  grammars::ABSOLUTE.to_vec()),
-                (0xbd,  grammars::ABSOLUTEX.to_vec()),
-                (0xa9,  grammars::IMMEDIAT1BYTE.to_vec()),
-
-            ],
-        });
+// Opcode
+// one row of an instruction table,
+// a mnemonic in one addressing mode
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Opcode
+{
+    pub mnemonic: String,
+    pub mode: AddressingMode,
+    pub opcode: u8,
+    pub bytes: u32,
+    pub cycles: u32,        // without page crossing or taken branches
+    pub flags: String,      // the flags it changes, - for none
+    pub set: String,        // which cpus have it, see Cpu::instruction_sets
+}
 
+impl Opcode
+{
+    // builtin
+    // every instruction of every
+    // cpu the assembler knows
+    pub fn builtin() -> &'static Vec<Opcode>
+    {
+        static TABLE: OnceLock<Vec<Opcode>> = OnceLock::new();
+        TABLE.get_or_init(|| Opcode::parse(BUILTIN_TABLE, "instructions.txt").expect("the built in instruction table is broken"))
+    }
 
-         // ldx
-         map.insert("ldx".to_string(), Instruction{
-            string_code:"ldx".to_string(),
-            opcode_grammer: vec![
-                (0xa2,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0xa6,  grammars::ZEROPAGE.to_vec()),
-                (0xb6,  grammars::ZEROPAGEY.to_vec()),
-                (0xae,  grammars::ABSOLUTE.to_vec()),
-                (0xbe,  grammars::ABSOLUTEY.to_vec()),
-            ],
-        });
+    // load
+    // reads an instruction table from a file,
+    // for cpus the assembler doesn't know
+    pub fn load(file_name: &str) -> Result<Vec<Opcode>, Diagnostic>
+    {
+        match fs::read_to_string(file_name)
+        {
+            Ok(text) => Opcode::parse(&text, file_name),
+            Err(err) =>
+            {
+                let mut diagnostic = Diagnostic::new(codes::IO, &format!("Couldn't read instruction table {}: {}", file_name, err), "Instructions");
+                diagnostic.file = Some(file_name.to_string());
+                Err(diagnostic)
+            }
+        }
+    }
 
-         // ldy
-         map.insert("ldy".to_string(), Instruction{
-            string_code:"ldy".to_string(),
-            opcode_grammer: vec![
-                (0xa0,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0xa4,  grammars::ZEROPAGE.to_vec()),
-                (0xb4,  grammars::ZEROPAGEX.to_vec()),
-                (0xac,  grammars::ABSOLUTE.to_vec()),
-                (0xbc,  grammars::ABSOLUTEX.to_vec()),
-            ],
-        });
+    // parse
+    // reads an instruction table, a row per line of
+    // mnemonic mode opcode bytes cycles flags set with
+    // # or ; starting a comment
+    pub fn parse(text: &str, file_name: &str) -> Result<Vec<Opcode>, Diagnostic>
+    {
+        let mut opcodes = vec![];
+
+        for (i, line) in text.lines().enumerate()
+        {
+            let line = line.split(['#', ';']).next().unwrap_or("");
+            let columns: Vec<&str> = line.split_whitespace().collect();
+
+            if columns.is_empty()
+            {
+                continue;
+            }
+
+            let error = |message: String|
+            {
+                let mut diagnostic = Diagnostic::new(codes::INSTRUCTION_TABLE, &message, "Instructions");
+                diagnostic.file = Some(file_name.to_string());
+                diagnostic.line = i as u32 + 1;
+                diagnostic
+            };
+
+            if columns.len() != 7
+            {
+                return Err(error(format!("Expected 7 columns: mnemonic mode opcode bytes cycles flags set, found {}", columns.len())));
+            }
+
+            let mnemonic = columns[0].to_lowercase();
+            if !mnemonic.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                return Err(error(format!("Mnemonic {} can only have letters and numbers", columns[0])));
+            }
+
+            let mode = match AddressingMode::from_name(columns[1])
+            {
+                Some(mode) => mode,
+                None =>
+                {
+                    let names: Vec<&str> = AddressingMode::all().iter().map(|m| m.name()).collect();
+                    return Err(error(format!("Unknown addressing mode {}, expected one of: {}", columns[1], names.join(", "))));
+                }
+            };
+
+            let opcode = u8::from_str_radix(columns[2].trim_start_matches('$'), 16)
+                .map_err(|_| error(format!("Opcode {} isn't a hex byte", columns[2])))?;
+            let bytes = columns[3].parse::<u32>()
+                .map_err(|_| error(format!("Bytes {} isn't a number", columns[3])))?;
+            let cycles = columns[4].parse::<u32>()
+                .map_err(|_| error(format!("Cycles {} isn't a number", columns[4])))?;
+
+            if bytes != mode.size()
+            {
+                return Err(error(format!("Bytes for {} {} should be {}, not {}", mnemonic.to_uppercase(), mode.name(), mode.size(), bytes)));
+            }
+
+            opcodes.push(Opcode { mnemonic, mode, opcode, bytes, cycles, flags: columns[5].to_string(), set: columns[6].to_lowercase() });
+        }
+
+        Ok(opcodes)
+    }
 
+    // is_for
+    // true if the cpu has this instruction, a set
+    // no built in cpu has is a custom one and always on
+    pub fn is_for(&self, cpu: Cpu) -> bool
+    {
+        cpu.instruction_sets().contains(&self.set.as_str())
+            || !Cpu::all().iter().any(|c| c.instruction_sets().contains(&self.set.as_str()))
+    }
 
+    // is_wide
+    // true if its immediate takes 2 bytes on a
+    // 65816 with the registers at the given widths
+    pub fn is_wide(&self, accumulator_16: bool, index_16: bool) -> bool
+    {
+        self.mode == AddressingMode::Immediate
+            && ((accumulator_16 && ACCUMULATOR_WIDTH.contains(&self.mnemonic.as_str()))
+                || (index_16 && INDEX_WIDTH.contains(&self.mnemonic.as_str())))
+    }
+}
 
-        // lsr
-        map.insert("lsr".to_string(), Instruction{
-            string_code:"lsr".to_string(),
-            opcode_grammer: vec![
-                (0x4a,  grammars::ACCUMULATOR.to_vec()),
-                (0x46,  grammars::ZEROPAGE.to_vec()),
-                (0x56,  grammars::ZEROPAGEX.to_vec()),
-                (0x4e,  grammars::ABSOLUTE.to_vec()),
-                (0x5e,  grammars::ABSOLUTEX.to_vec()),
-            ],
-        });
 
+// instruction
+// holds a sring code i.e. "str", "and", etc.
+// holds a hashmap full of possible grammars that this could be
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Instruction
+{
+    pub string_code: String,
+    pub opcode_grammer: Vec<(u8, Vec<TokenType>)>,
+    pub modes: Vec<AddressingMode>,     // the mode of each grammar
+}
 
-        // nop
-        map.insert("nop".to_string(), Instruction{
-            string_code:"nop".to_string(),
-            opcode_grammer: vec![
-                (0xea,  grammars::EMPTY.to_vec()),
-            ],
-        });
 
 
-        // ora
-        map.insert("ora".to_string(), Instruction{
-            string_code:"ora".to_string(),
-            opcode_grammer: vec![
-                (0x01,  grammars::INDIRECTX.to_vec()),
-                (0x11,  grammars::INDIRECTY.to_vec()),
-                (0x09,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0x05,  grammars::ZEROPAGE.to_vec()),
-                (0x15,  grammars::ZEROPAGEX.to_vec()),
-                (0x0d,  grammars::ABSOLUTE.to_vec()),
-                (0x1d,  grammars::ABSOLUTEX.to_vec()),
-                (0x19,  grammars::ABSOLUTEY.to_vec()),
-            ],
-        });
+impl Instruction
+{
+    // is_branch
+    // branches take an address but store
+    // how far away it is in one byte
+    pub fn is_branch(&self) -> bool
+    {
+        self.modes.iter().any(|m| matches!(m, AddressingMode::Relative | AddressingMode::ZeroPageRelative))
+    }
 
+    // is_bit_branch
+    // BBR and BBS test a bit of a zero page
+    // byte then branch, the byte comes first
+    pub fn is_bit_branch(&self) -> bool
+    {
+        self.modes.contains(&AddressingMode::ZeroPageRelative)
+    }
 
-        // tax
-        map.insert("tax".to_string(), Instruction{
-            string_code:"tax".to_string(),
-            opcode_grammer: vec![
-                (0xaa,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // is_long_branch
+    // BRL and PER store how far away
+    // their address is in two bytes
+    pub fn is_long_branch(&self) -> bool
+    {
+        self.modes.contains(&AddressingMode::LongRelative)
+    }
 
-        // txa
-        map.insert("txa".to_string(), Instruction{
-            string_code:"txa".to_string(),
-            opcode_grammer: vec![
-                (0x8a,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // is_block_move
+    // MVN and MVP take a source and
+    // destination bank
+    pub fn is_block_move(&self) -> bool
+    {
+        self.modes.contains(&AddressingMode::BlockMove)
+    }
 
-        // dex
-        map.insert("dex".to_string(), Instruction{
-            string_code:"dex".to_string(),
-            opcode_grammer: vec![
-                (0xca,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // uses_accumulator_width
+    // on the 65816 these have a 2 byte
+    // immediate when the accumulator is 16 bits
+    pub fn uses_accumulator_width(&self) -> bool
+    {
+        ACCUMULATOR_WIDTH.contains(&self.string_code.as_str())
+    }
 
-        // inx
-        map.insert("inx".to_string(), Instruction{
-            string_code:"inx".to_string(),
-            opcode_grammer: vec![
-                (0xe8,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // uses_index_width
+    // on the 65816 these have a 2 byte immediate
+    // when the index registers are 16 bits
+    pub fn uses_index_width(&self) -> bool
+    {
+        INDEX_WIDTH.contains(&self.string_code.as_str())
+    }
 
-        // tay
-        map.insert("tay".to_string(), Instruction{
-            string_code:"tay".to_string(),
-            opcode_grammer: vec![
-                (0xa8,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // with_wide_immediate
+    // the same instruction but its immediate
+    // mode takes 2 bytes
+    pub fn with_wide_immediate(mut self) -> Instruction
+    {
+        for grammar in &mut self.opcode_grammer
+        {
+            if grammar.1 == grammars::IMMEDIAT1BYTE
+            {
+                grammar.1 = grammars::IMMEDIAT2BYTE.to_vec();
+            }
+        }
+        self
+    }
 
 
-        // tya
-        map.insert("tya".to_string(), Instruction{
-            string_code:"tya".to_string(),
-            opcode_grammer: vec![
-                (0x98,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // opcodes
+    // the rows of the instruction table the cpu has
+    // with the extra ones after, a row for a mnemonic
+    // and mode that is already there replaces it
+    pub fn opcodes(cpu: Cpu, extra: &[Opcode]) -> Vec<Opcode>
+    {
+        // the built in rows of each cpu only need working out once
+        static BUILTIN: OnceLock<Vec<Vec<Opcode>>> = OnceLock::new();
+        let builtin = BUILTIN.get_or_init(|| Cpu::all().into_iter().map(|c| Instruction::merge(c, Opcode::builtin())).collect());
+        let builtin = &builtin[Cpu::all().iter().position(|c| *c == cpu).unwrap()];
 
-        // dey
-        map.insert("dey".to_string(), Instruction{
-            string_code:"dey".to_string(),
-            opcode_grammer: vec![
-                (0x88,  grammars::EMPTY.to_vec()),
-            ],
-        });
+        if extra.is_empty()
+        {
+            return builtin.clone();
+        }
 
-        // iny
-        map.insert("iny".to_string(), Instruction{
-            string_code:"iny".to_string(),
-            opcode_grammer: vec![
-                (0xc8,  grammars::EMPTY.to_vec()),
-            ],
-        });
+        Instruction::merge(cpu, &[builtin.as_slice(), extra].concat())
+    }
 
-        // rol
-        map.insert("rol".to_string(), Instruction{
-            string_code:"rol".to_string(),
-            opcode_grammer: vec![
-                (0x2a,  grammars::ACCUMULATOR.to_vec()),
-                (0x26,  grammars::ZEROPAGE.to_vec()),
-                (0x36,  grammars::ZEROPAGEX.to_vec()),
-                (0x2e,  grammars::ABSOLUTE.to_vec()),
-                (0x3e,  grammars::ABSOLUTEX.to_vec()),
-            
-            ],
-        });
+    // merge
+    // the rows the cpu has in order, a later row for
+    // a mnemonic and mode already seen replaces it
+    fn merge(cpu: Cpu, rows: &[Opcode]) -> Vec<Opcode>
+    {
+        let mut opcodes: Vec<Opcode> = vec![];
+        let mut index: HashMap<(String, AddressingMode), usize> = HashMap::new();
+
+        for row in rows.iter().filter(|o| o.is_for(cpu))
+        {
+            match index.get(&(row.mnemonic.to_string(), row.mode))
+            {
+                Some(i) => opcodes[*i] = row.clone(),
+                None =>
+                {
+                    index.insert((row.mnemonic.to_string(), row.mode), opcodes.len());
+                    opcodes.push(row.clone());
+                }
+            }
+        }
+
+        opcodes
+    }
 
+    // get_map
+    // returns a list
+    // returns a map of instruction string codes
+    // paired with their instruction struct
+    // for the given cpu
+    pub fn get_map(cpu: Cpu) -> HashMap<String,Instruction>
+    {
+        Instruction::get_map_with(cpu, &[])
+    }
 
-        // ror
-        map.insert("ror".to_string(), Instruction{
-            string_code:"ror".to_string(),
-            opcode_grammer: vec![
-                (0x6a,  grammars::ACCUMULATOR.to_vec()),
-                (0x66,  grammars::ZEROPAGE.to_vec()),
-                (0x76,  grammars::ZEROPAGEX.to_vec()),
-                (0x6e,  grammars::ABSOLUTE.to_vec()),
-                (0x7e,  grammars::ABSOLUTEX.to_vec()),
-            
-            ],
-        });
+    // get_map_with
+    // the same as get_map with the
+    // rows of a user's table on top
+    pub fn get_map_with(cpu: Cpu, extra: &[Opcode]) -> HashMap<String,Instruction>
+    {
+        let mut opcodes = Instruction::opcodes(cpu, extra);
+        opcodes.sort_by_key(|o| o.mode.priority());
 
+        let mut map: HashMap<String,Instruction> = HashMap::new();
+        for row in opcodes
+        {
+            let instruction = map.entry(row.mnemonic.to_string()).or_insert(Instruction{
+                string_code: row.mnemonic.to_string(),
+                opcode_grammer: vec![],
+                modes: vec![],
+            });
+            instruction.opcode_grammer.push((row.opcode, row.mode.grammar()));
+            instruction.modes.push(row.mode);
+        }
 
-        // rti
-        map.insert("rti".to_string(), Instruction{
-            string_code:"rti".to_string(),
-            opcode_grammer: vec![
-                (0x40,  grammars::EMPTY.to_vec()),
-            ],
-        });
+        map
+    }
 
+    // mnemonics
+    // every mnemonic the cpu has, sorted
+    pub fn mnemonics(cpu: Cpu) -> Vec<String>
+    {
+        let mut mnemonics: Vec<String> = Instruction::opcodes(cpu, &[]).into_iter().map(|o| o.mnemonic).collect();
+        mnemonics.sort();
+        mnemonics.dedup();
+        mnemonics
+    }
 
-        // rts
-        map.insert("rts".to_string(), Instruction{
-            string_code:"rts".to_string(),
-            opcode_grammer: vec![
-                (0x60,  grammars::EMPTY.to_vec()),
-            ],
+    // is_other_mnemonic
+    // true if the name is an instruction of the 65C02,
+    // the 65816 or the like but not of the 6502, labels
+    // named like these get a warning
+    pub fn is_other_mnemonic(name: &str) -> bool
+    {
+        static OTHER: OnceLock<HashSet<String>> = OnceLock::new();
+        let other = OTHER.get_or_init(|| {
+            let mos6502 = Instruction::mnemonics(Cpu::Mos6502);
+            Opcode::builtin().iter().map(|o| o.mnemonic.to_string()).filter(|m| !mos6502.contains(m)).collect()
         });
 
+        other.contains(&name.to_lowercase())
+    }
 
+    // reference
+    // a markdown table of everything
+    // the cpu has, for documentation
+    pub fn reference(cpu: Cpu, extra: &[Opcode]) -> String
+    {
+        let mut opcodes = Instruction::opcodes(cpu, extra);
+        opcodes.sort_by(|a, b| a.mnemonic.cmp(&b.mnemonic));
 
-        // sbc
-        map.insert("sbc".to_string(), Instruction{
-            string_code:"sbc".to_string(),
-            opcode_grammer: vec![
-                (0xe1,  grammars::INDIRECTX.to_vec()),
-                (0xf1,  grammars::INDIRECTY.to_vec()),
-                (0xe9,  grammars::IMMEDIAT1BYTE.to_vec()),
-                (0xe5,  grammars::ZEROPAGE.to_vec()),
-                (0xf5,  grammars::ZEROPAGEX.to_vec()),
-                (0xed,  grammars::ABSOLUTE.to_vec()),
-                (0xfd,  grammars::ABSOLUTEX.to_vec()),
-                (0xf9,  grammars::ABSOLUTEY.to_vec()),
-            ],
-        });
-
+        let mut reference = format!("# {} instructions\n\n", cpu);
+        reference += "| Instruction | Mode | Opcode | Bytes | Cycles | Flags |\n";
+        reference += "|-------------|------|--------|-------|--------|-------|\n";
 
-        // sta
-        map.insert("sta".to_string(), Instruction{
-            string_code:"sta".to_string(),
-            opcode_grammer: vec![
-                (0x81,  grammars::INDIRECTX.to_vec()),
-                (0x91,  grammars::INDIRECTY.to_vec()),
-                (0x85,  grammars::ZEROPAGE.to_vec()),
-                (0x95,  grammars::ZEROPAGEX.to_vec()),
-                (0x8d,  grammars::ABSOLUTE.to_vec()),
-                (0x9d,  grammars::ABSOLUTEX.to_vec()),
-                (0x99,  grammars::ABSOLUTEY.to_vec()),            
-            ],
-        });
+        for o in opcodes
+        {
+            let written = format!("{} {}", o.mnemonic.to_uppercase(), o.mode.syntax());
+            reference += &format!("| `{}` | {} | ${:02X} | {} | {} | {} |\n", written.trim_end(), o.mode.name(), o.opcode, o.bytes, o.cycles, o.flags);
+        }
 
-         // txs
-         map.insert("txs".to_string(), Instruction{
-            string_code:"txs".to_string(),
-            opcode_grammer: vec![
-                (0x9a,  grammars::EMPTY.to_vec()),
-            ],
-        });
+        reference
+    }
+}
 
-        // tsx
-        map.insert("tsx".to_string(), Instruction{
-            string_code:"tsx".to_string(),
-            opcode_grammer: vec![
-                (0xba,  grammars::EMPTY.to_vec()),
-            ],
-        });
 
-        // pha
-        map.insert("pha".to_string(), Instruction{
-            string_code:"pha".to_string(),
-            opcode_grammer: vec![
-                (0x48,  grammars::EMPTY.to_vec()),
-            ],
-        });
+#[cfg(test)]
+mod tests
+{
+    use super::*;
 
-        // pla
-        map.insert("pla".to_string(), Instruction{
-            string_code:"pla".to_string(),
-            opcode_grammer: vec![
-                (0x68,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    // table_error
+    // the diagnostic from parsing a one row table
+    fn table_error(row: &str) -> Diagnostic
+    {
+        Opcode::parse(&format!("# a comment\n{}\n", row), "mine.txt").unwrap_err()
+    }
 
-        // php
-        map.insert("php".to_string(), Instruction{
-            string_code:"php".to_string(),
-            opcode_grammer: vec![
-                (0x08,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    #[test]
+    fn parse_a_custom_table()
+    {
+        let opcodes = Opcode::parse("# a board with a halt instruction\n\nhlt  imp  02  1  2  -  mycpu ; stops\n", "mine.txt").unwrap();
+
+        assert_eq!(opcodes, vec![Opcode {
+            mnemonic: "hlt".to_string(),
+            mode: AddressingMode::Implied,
+            opcode: 0x02,
+            bytes: 1,
+            cycles: 2,
+            flags: "-".to_string(),
+            set: "mycpu".to_string(),
+        }]);
+    }
 
-         // plp
-         map.insert("plp".to_string(), Instruction{
-            string_code:"plp".to_string(),
-            opcode_grammer: vec![
-                (0x28,  grammars::EMPTY.to_vec()),
-            ],
-        });
+    #[test]
+    fn bad_rows_are_errors_with_the_line()
+    {
+        for row in ["hlt imp 2G 1 2 - mycpu", "hlt imp 02 x 2 - mycpu", "hlt imp 02 2 2 - mycpu", "hlt imp 02 1 2 -", "hlt nope 02 1 2 - mycpu"]
+        {
+            let error = table_error(row);
+            assert_eq!(error.code, codes::INSTRUCTION_TABLE, "{}", row);
+            assert_eq!(error.file.as_deref(), Some("mine.txt"), "{}", row);
+            assert_eq!(error.line, 2, "{}", row);
+        }
+
+        assert!(table_error("hlt imp 02 2 2 - mycpu").message.contains("should be 1"));
+    }
 
+    #[test]
+    fn missing_table_is_an_io_error()
+    {
+        assert_eq!(Opcode::load("/nonexistent/instructions.txt").unwrap_err().code, codes::IO);
+    }
 
-        // stx
-        map.insert("stx".to_string(), Instruction{
-            string_code:"stx".to_string(),
-            opcode_grammer: vec![
-                (0x86,  grammars::ZEROPAGE.to_vec()),
-                (0x96,  grammars::ZEROPAGEY.to_vec()),
-                (0x8e,  grammars::ABSOLUTE.to_vec()),
-              ],
-        });
+    #[test]
+    fn custom_rows_are_added_and_replace_built_in_ones()
+    {
+        let extra = Opcode::parse("hlt imp 02 1 2 - mycpu\nlda imm 12 2 2 NZ 6502\n", "mine.txt").unwrap();
+        let map = Instruction::get_map_with(Cpu::Mos6502, &extra);
 
-        // sty
-        map.insert("sty".to_string(), Instruction{
-            string_code:"sty".to_string(),
-            opcode_grammer: vec![
-                (0x84,  grammars::ZEROPAGE.to_vec()),
-                (0x94,  grammars::ZEROPAGEX.to_vec()),
-                (0x8c,  grammars::ABSOLUTE.to_vec()),
-              ],
-        });
+        assert_eq!(map["hlt"].opcode_grammer, vec![(0x02, AddressingMode::Implied.grammar())]);
+        assert!(map["lda"].opcode_grammer.contains(&(0x12, AddressingMode::Immediate.grammar())));
+        assert!(!map["lda"].opcode_grammer.iter().any(|g| g.0 == 0xa9));
 
+        // the built in table is untouched
+        assert!(Instruction::get_map(Cpu::Mos6502)["lda"].opcode_grammer.iter().any(|g| g.0 == 0xa9));
+    }
 
+    #[test]
+    fn each_cpu_has_its_own_mnemonics()
+    {
+        let mos6502 = Instruction::mnemonics(Cpu::Mos6502);
+        assert_eq!(mos6502.len(), 56);
+        assert!(!mos6502.contains(&"phx".to_string()));
+
+        assert!(Instruction::mnemonics(Cpu::Mos6502X).contains(&"lax".to_string()));
+        assert!(Instruction::mnemonics(Cpu::Cmos65C02).contains(&"phx".to_string()));
+        assert!(!Instruction::mnemonics(Cpu::Cmos65C02).contains(&"rmb0".to_string()));
+        assert!(Instruction::mnemonics(Cpu::Rockwell65C02).contains(&"rmb0".to_string()));
+        assert!(!Instruction::mnemonics(Cpu::Wdc65816).contains(&"rmb0".to_string()));
+        assert!(Instruction::mnemonics(Cpu::Wdc65816).contains(&"xce".to_string()));
+        assert!(!Instruction::mnemonics(Cpu::Ricoh2A03).contains(&"phx".to_string()));
+
+        // worked out once and the same every time after
+        assert_eq!(Instruction::opcodes(Cpu::HuC6280, &[]), Instruction::opcodes(Cpu::HuC6280, &[]));
+    }
 
-        map
+    #[test]
+    fn other_mnemonics_are_not_the_6502s()
+    {
+        assert!(Instruction::is_other_mnemonic("PHX"));
+        assert!(Instruction::is_other_mnemonic("xce"));
+        assert!(!Instruction::is_other_mnemonic("lda"));
+        assert!(!Instruction::is_other_mnemonic("start"));
     }
 }
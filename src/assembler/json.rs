@@ -0,0 +1,69 @@
+
+// json
+// just enough json writing for the reports,
+// not worth another dependency
+
+
+// string
+// quotes and escapes a string for json
+pub fn string(text: &str) -> String
+{
+    let mut out = "\"".to_string();
+
+    for c in text.chars()
+    {
+        match c
+        {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+
+    out + "\""
+}
+
+// array
+// joins already written json values into an array
+pub fn array(values: &[String]) -> String
+{
+    format!("[{}]", values.join(","))
+}
+
+// object
+// writes an object from keys and already written json values
+pub fn object(fields: &[(&str, String)]) -> String
+{
+    let fields: Vec<String> = fields.iter().map(|(key, value)| format!("{}:{}", string(key), value)).collect();
+    format!("{{{}}}", fields.join(","))
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn strings_are_quoted_and_escaped()
+    {
+        assert_eq!(string("plain"), "\"plain\"");
+        assert_eq!(string("say \"hi\"\\n"), "\"say \\\"hi\\\"\\\\n\"");
+        assert_eq!(string("a\tb\nc\r"), "\"a\\tb\\nc\\r\"");
+        assert_eq!(string("\u{1}"), "\"\\u0001\"");
+        assert_eq!(string("é"), "\"é\"");
+    }
+
+    #[test]
+    fn arrays_and_objects()
+    {
+        assert_eq!(array(&[]), "[]");
+        assert_eq!(array(&["1".to_string(), string("two")]), "[1,\"two\"]");
+        assert_eq!(object(&[]), "{}");
+        assert_eq!(object(&[("start", "1536".to_string()), ("names", array(&[string("a")]))]), "{\"start\":1536,\"names\":[\"a\"]}");
+    }
+}
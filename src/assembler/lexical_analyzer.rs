@@ -5,7 +5,9 @@ use std::io::{BufReader, BufRead};
 use std::fmt;
 
 
-use crate::assembler::gen_errors::GeneralError;
+use crate::assembler::diagnostic::{Diagnostic, codes};
+use crate::assembler::instruction::Instruction;
+use crate::assembler::cpu::Cpu;
 
 
 
@@ -22,10 +24,14 @@ pub  struct LexicalAnalyzer
     returned_eof: bool,                 //
     return_eol: bool,
     token_parsers: Vec<TokenParser>, 
+    token_regexes: Vec<Regex>,          // the token parsers compiled, in the same order
     remove_comments: bool,
     pub file_line: u32,
     logical_line: u32,
     current_line_new: bool,
+    line_offset: u32,                   // characters trimmed and parsed off the front of current_line
+    code_end: u32,                      // where the last token that wasn't a comment ended
+    eol_column: u32,                    // where the line ended, for the eol token
 }
 
 // token
@@ -41,6 +47,8 @@ pub struct Token
 
     pub logical_line: u32,      // logical line doesn't include newlines or comment lines
     pub file_line: u32,         // file line includes newlines and comment lines 
+    pub column: u32,            // where it starts on the file line, from 1, 0 when it isn't on one
+    pub length: u32,            // how many characters it is in the file
 }
 
 // implement display
@@ -61,6 +69,8 @@ impl Token
             value: "".to_string(),
             logical_line:0,
             file_line:0,
+            column:0,
+            length:0,
         }
     }
 }
@@ -78,6 +88,7 @@ pub enum TokenType
     RegX,
     RegY,
     RegA,
+    RegS,          // the 65816 stack pointer in sr,S, lexed as a label and turned into this by the assembler
     EQUALS,
     PLUS,
     MINUS,
@@ -85,12 +96,15 @@ pub enum TokenType
     DIVIDE,
     Num1Bytes,
     Num2Bytes,
+    Num3Bytes,     // 65816 long addresses
     Character,
     String,
     Hash,          // tells us it is immidiete addressing 
     Comment,           
     LeftParenth,
     RightParenth,
+    LeftBracket,   // 65816 [dp] indirect long
+    RightBracket,
     Comma,
     Collon,
     Label, 
@@ -109,6 +123,7 @@ impl fmt::Display for TokenType {
             TokenType::RegX => write!(f, "RegX"),
             TokenType::RegY => write!(f, "RegY"),
             TokenType::RegA => write!(f, "RegA"),
+            TokenType::RegS => write!(f, "RegS"),
             TokenType::EQUALS => write!(f, "Equals sign"),
             TokenType::PLUS => write!(f, "Plus sign"),
             TokenType::MINUS => write!(f, "Minus sign"),
@@ -116,12 +131,15 @@ impl fmt::Display for TokenType {
             TokenType::DIVIDE => write!(f, "Divide sign"),
             TokenType::Num2Bytes => write!(f, "Num2Bytes"),
             TokenType::Num1Bytes => write!(f, "Num1ytes"),
+            TokenType::Num3Bytes => write!(f, "Num3Bytes"),
             TokenType::String => write!(f, "String"),
             TokenType::Character => write!(f, "Character"),
             TokenType::Hash => write!(f, "Hash"),
             TokenType::Comment => write!(f, "Comment"),
             TokenType::LeftParenth => write!(f, "LeftParenth"),
             TokenType::RightParenth => write!(f, "RightParenth"),
+            TokenType::LeftBracket => write!(f, "LeftBracket"),
+            TokenType::RightBracket => write!(f, "RightBracket"),
             TokenType::Comma => write!(f, "Comma"),
             TokenType::Collon => write!(f, "Collon"),
             TokenType::Label => write!(f, "Label"),
@@ -134,6 +152,47 @@ impl fmt::Display for TokenType {
     }
 }
 
+impl TokenType
+{
+    // describe
+    // the token type in words for
+    // error messages, i.e. "end of line"
+    pub fn describe(&self) -> &'static str
+    {
+        match self {
+            TokenType::Instruction => "an instruction",
+            TokenType::RegX => "`X`",
+            TokenType::RegY => "`Y`",
+            TokenType::RegA => "`A`",
+            TokenType::RegS => "`S`",
+            TokenType::EQUALS => "`=`",
+            TokenType::PLUS => "`+`",
+            TokenType::MINUS => "`-`",
+            TokenType::TIMES => "`*`",
+            TokenType::DIVIDE => "`/`",
+            TokenType::Num1Bytes => "a 1 byte number",
+            TokenType::Num2Bytes => "a 2 byte number",
+            TokenType::Num3Bytes => "a 3 byte number",
+            TokenType::String => "a string",
+            TokenType::Character => "a character",
+            TokenType::Hash => "`#`",
+            TokenType::Comment => "a comment",
+            TokenType::LeftParenth => "`(`",
+            TokenType::RightParenth => "`)`",
+            TokenType::LeftBracket => "`[`",
+            TokenType::RightBracket => "`]`",
+            TokenType::Comma => "`,`",
+            TokenType::Collon => "`:`",
+            TokenType::Label => "a label",
+            TokenType::Directive => "a directive",
+            TokenType::Garbage => "something unrecognized",
+            TokenType::EOF => "end of file",
+            TokenType::EOL => "end of line",
+            TokenType::Empty => "nothing",
+        }
+    }
+}
+
 // TokenParser
 // struct that holds a regular expression string
 // and the type of token that regular expresssion 
@@ -154,17 +213,19 @@ impl LexicalAnalyzer
     // new 
     // returns a new lexical 
     // analyzer
-    pub fn new(file_name:String, remove_comm: bool) -> Result<LexicalAnalyzer, GeneralError>
+    pub fn new(file_name:String, remove_comm: bool) -> Result<LexicalAnalyzer, Diagnostic>
     {
         let file_result = File::open(&file_name);
         let file_;
         match file_result
         {
-            Err(_) => return Err(LexicalAnalyzer::error("File doesn't exist!")),
+            Err(_) => return Err(LexicalAnalyzer::error(codes::IO, "File doesn't exist!")),
             Ok(f) => file_ = f,
         }
 
-        
+        let token_parsers = LexicalAnalyzer::get_token_parsers();
+        let token_regexes = token_parsers.iter().map(|p| Regex::new(p.reg.as_str()).unwrap()).collect();
+
         Ok(LexicalAnalyzer 
         {
             reader: Box::new(BufReader::new(file_)),
@@ -172,11 +233,15 @@ impl LexicalAnalyzer
             return_eof: false,
             returned_eof: false,
             return_eol: false,
-            token_parsers: LexicalAnalyzer::get_token_parsers(),
+            token_parsers,
+            token_regexes,
             remove_comments: remove_comm,
             logical_line:0,
             file_line:0,
-            current_line_new: true
+            current_line_new: true,
+            line_offset: 0,
+            code_end: 0,
+            eol_column: 0,
         })
     }
 
@@ -184,7 +249,7 @@ impl LexicalAnalyzer
     // get_line
     // private function that
     // getsline 
-    fn get_line(& mut self) -> Result<(), GeneralError>
+    fn get_line(& mut self) -> Result<(), Diagnostic>
     {
 
 
@@ -202,7 +267,7 @@ impl LexicalAnalyzer
 
                 // something bad happened 
                 Err(_) => {
-                    return Err(LexicalAnalyzer::error("Something bad happened reading the file!"));
+                    return Err(LexicalAnalyzer::error(codes::IO, "Something bad happened reading the file!"));
                 },
                 // eof
                 // just return 
@@ -212,9 +277,14 @@ impl LexicalAnalyzer
                 }
                 // there is actual data 
                 _ => {
+                    // columns count from the start of
+                    // the line as it is in the file
+                    self.line_offset = 0;
+                    self.code_end = 0;
+
                     // trim it 
                     // this will remove newlines and make it an empty string if there is nothing there 
-                    self.current_line = self.current_line.trim().to_string();
+                    self.current_line = self.current_line.trim_end().to_string();
                 }
             }
 
@@ -234,7 +304,7 @@ impl LexicalAnalyzer
     // using regular expressions
     // this bad boy creates a token
     // removes it from the current line
-    fn parse_next_token(& mut self) -> Result<Token, GeneralError>
+    fn parse_next_token(& mut self) -> Result<Token, Diagnostic>
     {
         // get a line 
         // if we dont already have one
@@ -244,7 +314,9 @@ impl LexicalAnalyzer
             Err(err) => return Err(err),
         }
 
-        self.current_line = self.current_line.trim().to_string();
+        let trimmed = self.current_line.trim_start().to_string();
+        self.line_offset += (self.current_line.chars().count() - trimmed.chars().count()) as u32;
+        self.current_line = trimmed;
 
         let op = self.return_eol_eof_if();
         if let Some(returnable) = op
@@ -253,10 +325,8 @@ impl LexicalAnalyzer
         }
 
 
-        for p in &self.token_parsers 
+        for (p, reg) in self.token_parsers.iter().zip(&self.token_regexes)
         {
-                   // create the regx
-        let reg = Regex::new(p.reg.as_str()).unwrap();
         let found_option = reg.find(&self.current_line).unwrap();
         
 
@@ -272,9 +342,19 @@ impl LexicalAnalyzer
                 // shouldn't be that slow, later mabye I'll figure something else out 
                 let captured_text = caps.as_str().trim().to_string().clone();
 
-                // remove that item from the current line 
+                // remove that item from the current line,
+                // the parsers only match at the start of it
                 self.current_line = self.current_line.replacen(&captured_text, "",1);
 
+                let column = self.line_offset + 1;
+                let length = captured_text.chars().count() as u32;
+                self.line_offset += length;
+
+                if p.token_type != TokenType::Comment
+                {
+                    self.code_end = self.line_offset;
+                }
+
                 
                 if self.current_line == ""
                 {
@@ -284,6 +364,7 @@ impl LexicalAnalyzer
                     if !(self.current_line_new && p.token_type == TokenType::Comment && self.remove_comments)
                      {   
                         self.return_eol = true;
+                        self.eol_column = self.code_end + 1;
                      }
               
                 }
@@ -309,7 +390,7 @@ impl LexicalAnalyzer
 
             
                 
-                return Ok(Token{token_type:p.token_type, value: captured_text.to_string(), logical_line: self.logical_line, file_line: self.file_line,});
+                return Ok(Token{token_type:p.token_type, value: captured_text.to_string(), logical_line: self.logical_line, file_line: self.file_line, column, length});
             },  
 
             // do nothing if it didn't find anying 
@@ -321,7 +402,7 @@ impl LexicalAnalyzer
         // this should never happen, because there is a garbage token
         // that collects everything left, but in the case I messed up that
         // regular expression this will catch 
-        Err(LexicalAnalyzer::error("No token parsed! Something is wrong with the parsers"))
+        Err(LexicalAnalyzer::error(codes::INTERNAL, "No token parsed! Something is wrong with the parsers"))
     }   
 
     // get_token_parsers
@@ -333,14 +414,19 @@ impl LexicalAnalyzer
         vec![
             TokenParser{reg: r"((^\/\/)|^;)[\w\W]*".to_string(),
             token_type:TokenType::Comment},
-            TokenParser{reg:r"^(?i)(adc|and|asl|bcc|bcs|beq|bit|bmi|bne|bpl|brk|bvc|bvs|clc|cld|cli|clv|cmp|cpx|cpy|dec|dex|dey|eor|inc|inx|iny|jmp|jsr|lda|ldx|ldy|lsr|nop|ora|pha|php|pla|plp|rol|ror|rti|rts|sbc|sec|sed|sei|sta|stx|sty|tax|tay|tsx|txa|txs|tya)((?=\W)|(?=\s)|\z)".to_string(), 
+            // other cpus' mnemonics come through as labels
+            TokenParser{reg: format!(r"^(?i)({})((?=\W)|(?=\s)|\z)", Instruction::mnemonics(Cpu::Mos6502).join("|")),
                         token_type:TokenType::Instruction},
-            TokenParser{reg: r"^\.[a-zA-Z]+((?=\W)|(?=\s)|\z)".to_string(),
+            TokenParser{reg: r"^\.[a-zA-Z][a-zA-Z0-9]*((?=\W)|(?=\s)|\z)".to_string(),
                         token_type:TokenType::Directive},
             TokenParser{reg: r"^\(".to_string(),
                         token_type:TokenType::LeftParenth},
             TokenParser{reg: r"^\)".to_string(), 
                         token_type:TokenType::RightParenth},
+            TokenParser{reg: r"^\[".to_string(),
+                        token_type:TokenType::LeftBracket},
+            TokenParser{reg: r"^\]".to_string(),
+                        token_type:TokenType::RightBracket},
             TokenParser{reg: r"^#".to_string(),
                         token_type:TokenType::Hash},
             TokenParser{reg: r"^\=".to_string(),
@@ -371,7 +457,9 @@ impl LexicalAnalyzer
                         token_type:TokenType::Num1Bytes},
             TokenParser{reg:r"((^\$([0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f][0-9A-Fa-f]))|(^[0-9]+))((?=\W)|(?=\s)|\z)".to_string(),
                         token_type:TokenType::Num2Bytes},
-            TokenParser{reg:r"^([0-9A-za-z$#@!?&^%~\.;\[\]])+((?=\W)|(?=\s)|\z)".to_string(),
+            TokenParser{reg:r"^\$[0-9A-Fa-f]{5,6}((?=\W)|(?=\s)|\z)".to_string(),
+                        token_type:TokenType::Num3Bytes},
+            TokenParser{reg:r"^([0-9A-Za-z_\\^`$#@!?&%~\.;])+((?=\W)|(?=\s)|\z)".to_string(),
                         token_type:TokenType::Label},
             TokenParser{reg:r"^[\w\W]+".to_string(),
                         token_type:TokenType::Garbage}  
@@ -383,7 +471,7 @@ impl LexicalAnalyzer
     // returns the eol if we are at an
     // eol
     // returns the eof if we are at the eof
-    fn return_eol_eof_if(& mut self) -> Option<Result<Token, GeneralError>>
+    fn return_eol_eof_if(& mut self) -> Option<Result<Token, Diagnostic>>
     {
 
         if self.return_eol
@@ -394,6 +482,8 @@ impl LexicalAnalyzer
                 value: "".to_string(),
                 logical_line: self.logical_line-1,
                 file_line: self.file_line-1,
+                column: self.eol_column,
+                length: 0,
             }));
         }
 
@@ -405,6 +495,8 @@ impl LexicalAnalyzer
                 value: "".to_string(),
                 logical_line: self.logical_line,
                 file_line: self.file_line,
+                column: 0,
+                length: 0,
             }));
         }
 
@@ -423,9 +515,9 @@ impl LexicalAnalyzer
     // error
     // just returns an error
     // with the from set to lexical
-    pub fn error(mssg: &str)-> GeneralError
+    pub fn error(code: &'static str, mssg: &str)-> Diagnostic
     {
-        GeneralError::new(mssg, "lexical")
+        Diagnostic::new(code, mssg, "lexical")
     }
 }
 
@@ -459,7 +551,7 @@ impl<'a> LexicalIterator
 impl<'a> Iterator  for  LexicalIterator
 {
 
-    type Item=Result<Token, GeneralError>;
+    type Item=Result<Token, Diagnostic>;
 
     fn next(&mut self) -> Option<Self::Item>
     {   
@@ -483,5 +575,70 @@ impl<'a> Iterator  for  LexicalIterator
 }
 
 
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // tokens
+    // lexes the source from a temporary file into
+    // the value, column and length of each token
+    fn tokens(name: &str, source: &str) -> Vec<(TokenType, String, u32, u32)>
+    {
+        let file = std::env::temp_dir().join(format!("asm_6502_lex_{}_{}.asm", name, std::process::id()));
+        std::fs::write(&file, source).unwrap();
+
+        let tokens = LexicalAnalyzer::new(file.to_str().unwrap().to_string(), true).unwrap().get_iterator()
+            .map(|t| t.unwrap())
+            .map(|t| (t.token_type, t.value, t.column, t.length))
+            .collect();
+
+        std::fs::remove_file(&file).ok();
+        tokens
+    }
+
+    // token
+    // an expected token
+    fn token(token_type: TokenType, value: &str, column: u32, length: u32) -> (TokenType, String, u32, u32)
+    {
+        (token_type, value.to_string(), column, length)
+    }
+
+    #[test]
+    fn columns_count_from_the_start_of_the_file_line()
+    {
+        assert_eq!(tokens("columns", "start:  LDA #$01 ; load\n"), vec![
+            token(TokenType::Label, "start", 1, 5),
+            token(TokenType::Collon, ":", 6, 1),
+            token(TokenType::Instruction, "LDA", 9, 3),
+            token(TokenType::Hash, "#", 13, 1),
+            token(TokenType::Num1Bytes, "$01", 14, 3),
+            token(TokenType::EOL, "", 17, 0),
+            token(TokenType::EOF, "", 0, 0),
+        ]);
+    }
 
+    #[test]
+    fn a_tab_is_one_column()
+    {
+        assert_eq!(tokens("tabs", "\tSTA ($10),Y\n"), vec![
+            token(TokenType::Instruction, "STA", 2, 3),
+            token(TokenType::LeftParenth, "(", 6, 1),
+            token(TokenType::Num1Bytes, "$10", 7, 3),
+            token(TokenType::RightParenth, ")", 10, 1),
+            token(TokenType::Comma, ",", 11, 1),
+            token(TokenType::RegY, "Y", 12, 1),
+            token(TokenType::EOL, "", 13, 0),
+            token(TokenType::EOF, "", 0, 0),
+        ]);
+    }
 
+    #[test]
+    fn columns_start_again_on_each_line()
+    {
+        let tokens = tokens("lines", "  NOP\n\n  .ORG $0600\n");
+        assert_eq!(tokens[0], token(TokenType::Instruction, "NOP", 3, 3));
+        assert_eq!(tokens[2], token(TokenType::Directive, ".ORG", 3, 4));
+        assert_eq!(tokens[3], token(TokenType::Num2Bytes, "$0600", 8, 5));
+    }
+}
@@ -0,0 +1,126 @@
+
+use std::collections::HashMap;
+use crate::assembler::insertable_num::InsertableNum;
+
+
+// how many bytes go on one row of the listing,
+// anything longer carries on to the next rows
+const BYTES_PER_ROW: usize = 4;
+
+
+// SourceLine
+// what one line of source did on the second pass,
+// the address is where its first byte (or its label) went
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct SourceLine
+{
+    pub file_line: u32,
+    pub address: Option<u32>,
+    pub bytes: Vec<u8>,
+}
+
+
+// listing
+// builds the listing text, every line of the source
+// with its line number, address and the bytes it emitted,
+// followed by the symbol table
+pub fn listing(file_name: &str, source: &str, lines: &[SourceLine], symbol_table: &HashMap<String, InsertableNum>) -> String
+{
+    let mut out = format!("; {}\n", file_name);
+    out += &format!("{:>5}  {:<4}  {:<12} {}\n", "Line", "Addr", "Bytes", "Source");
+
+    let by_line: HashMap<u32, &SourceLine> = lines.iter().map(|l| (l.file_line, l)).collect();
+
+    for (i, text) in source.lines().enumerate()
+    {
+        let file_line = i as u32 + 1;
+
+        match by_line.get(&file_line)
+        {
+            None => out += &format!("{:>5}  {:<4}  {:<12} {}\n", file_line, "", "", text),
+            Some(line) =>
+            {
+                let address = line.address.map(|a| format!("{:04X}", a)).unwrap_or_default();
+                let mut rows = line.bytes.chunks(BYTES_PER_ROW);
+
+                out += &format!("{:>5}  {:<4}  {:<12} {}\n", file_line, address, hex_bytes(rows.next().unwrap_or(&[])), text);
+
+                // the rest of a long .BYTE goes under it
+                let mut row_address = line.address.unwrap_or(0) + BYTES_PER_ROW as u32;
+                for row in rows
+                {
+                    out += &format!("{:>5}  {:04X}  {}\n", "", row_address, hex_bytes(row));
+                    row_address += BYTES_PER_ROW as u32;
+                }
+            }
+        }
+    }
+
+    out += "\n; Symbols\n";
+
+    let mut symbols: Vec<(&String, &InsertableNum)> = symbol_table.iter().collect();
+    symbols.sort_by(|a, b| a.0.cmp(b.0));
+
+    for (name, value) in symbols
+    {
+        match value
+        {
+            InsertableNum::Byte(num) => out += &format!("{:<24} ${:02X}\n", name, num),
+            InsertableNum::TwoByte(num) => out += &format!("{:<24} ${:04X}\n", name, num),
+            InsertableNum::ThreeByte(num) => out += &format!("{:<24} ${:06X}\n", name, num),
+        }
+    }
+
+    out
+}
+
+// hex_bytes
+// the bytes as space separated hex
+fn hex_bytes(bytes: &[u8]) -> String
+{
+    bytes.iter().map(|b| format!("{:02X}", b)).collect::<Vec<String>>().join(" ")
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    #[test]
+    fn rows_have_the_address_bytes_and_source()
+    {
+        let source = ".ORG $0600\nstart: LDA #$01\n; a comment\n  .BYTE 1,2,3,4,5,6\n  JMP start\n";
+        let lines = vec![
+            SourceLine { file_line: 2, address: Some(0x0600), bytes: vec![0xa9, 0x01] },
+            SourceLine { file_line: 4, address: Some(0x0602), bytes: vec![1, 2, 3, 4, 5, 6] },
+            SourceLine { file_line: 5, address: Some(0x0608), bytes: vec![0x4c, 0x00, 0x06] },
+        ];
+        let symbols = HashMap::from([("start".to_string(), InsertableNum::TwoByte(0x0600)), ("zp".to_string(), InsertableNum::Byte(0x10))]);
+
+        let text = listing("test.asm", source, &lines, &symbols);
+        let rows: Vec<&str> = text.lines().collect();
+
+        assert_eq!(rows[0], "; test.asm");
+        assert_eq!(rows[1], " Line  Addr  Bytes        Source");
+        assert_eq!(rows[2], "    1                     .ORG $0600");
+        assert_eq!(rows[3], "    2  0600  A9 01        start: LDA #$01");
+        assert_eq!(rows[4], "    3                     ; a comment");
+        assert_eq!(rows[5], "    4  0602  01 02 03 04    .BYTE 1,2,3,4,5,6");
+        assert_eq!(rows[6], "       0606  05 06");
+        assert_eq!(rows[7], "    5  0608  4C 00 06       JMP start");
+    }
+
+    #[test]
+    fn symbols_are_sorted_at_the_end()
+    {
+        let symbols = HashMap::from([("zp".to_string(), InsertableNum::Byte(0x10)), ("start".to_string(), InsertableNum::TwoByte(0x0600))]);
+
+        let text = listing("test.asm", "", &[], &symbols);
+        let rows: Vec<&str> = text.lines().skip_while(|l| *l != "; Symbols").collect();
+
+        assert_eq!(rows, vec!["; Symbols", "start                    $0600", "zp                       $10"]);
+    }
+}
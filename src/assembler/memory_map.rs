@@ -0,0 +1,207 @@
+
+use crate::assembler::object_code::ObjectCode;
+use crate::assembler::json;
+
+
+// where the interrupt vectors start,
+// they run to the top of memory
+const VECTORS_START: u32 = 0xfffa;
+
+
+// Region
+// a run of emitted bytes and how much room
+// there is before the next one starts
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Region
+{
+    pub start: u32,
+    pub end: u32,           // one past the last byte
+    pub free_after: u32,    // bytes up to the next region or the end of memory
+}
+
+impl Region
+{
+    pub fn size(&self) -> u32
+    {
+        self.end - self.start
+    }
+}
+
+
+// MemoryMap
+// every region the assembly emitted, sorted by
+// address, plus anything worth warning about
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct MemoryMap
+{
+    pub regions: Vec<Region>,
+    pub warnings: Vec<String>,
+}
+
+impl MemoryMap
+{
+    // new
+    // builds the map from the object code, io_ranges
+    // are the start and end (inclusive) of I/O space
+    pub fn new(object_code: &ObjectCode, io_ranges: &[(u32, u32)]) -> MemoryMap
+    {
+        let mut segments: Vec<(u32, u32)> = object_code.segments.iter().map(|s| (s.start, s.end())).collect();
+        segments.sort();
+
+        let mut regions: Vec<Region> = vec![];
+        let mut warnings: Vec<String> = vec![];
+
+        for (i, (start, end)) in segments.iter().enumerate()
+        {
+            let next_start = segments.get(i + 1).map(|n| n.0).unwrap_or(0x10000);
+
+            if next_start < *end
+            {
+                warnings.push(format!("${:04X}-${:04X} overlaps the region starting at ${:04X}", start, end - 1, next_start));
+            }
+
+            regions.push(Region { start: *start, end: *end, free_after: next_start.saturating_sub(*end) });
+        }
+
+        // a region that starts outside a range and runs into it spilled,
+        // one that starts right in it (like .ORG $FFFA) was put there on purpose
+        for region in &regions
+        {
+            for (io_start, io_end) in io_ranges
+            {
+                if region.start < *io_start && region.end > *io_start
+                {
+                    warnings.push(format!("${:04X}-${:04X} spills into I/O space at ${:04X}-${:04X}", region.start, region.end - 1, io_start, io_end));
+                }
+            }
+
+            if region.start < VECTORS_START && region.end > VECTORS_START
+            {
+                warnings.push(format!("${:04X}-${:04X} spills into the vectors at $FFFA-$FFFF", region.start, region.end - 1));
+            }
+        }
+
+        MemoryMap { regions, warnings }
+    }
+
+    // used
+    // total bytes emitted
+    pub fn used(&self) -> u32
+    {
+        self.regions.iter().map(|r| r.size()).sum()
+    }
+
+    // text
+    // the report as a table for people
+    pub fn text(&self) -> String
+    {
+        let mut out = format!("{:<7}{:<7}{:>6} {:>6}\n", "Start", "End", "Size", "Free");
+
+        for region in &self.regions
+        {
+            out += &format!("${:04X}  ${:04X}  {:>6} {:>6}\n", region.start, region.end - 1, region.size(), region.free_after);
+        }
+
+        out += &format!("\n{} bytes used in {} regions\n", self.used(), self.regions.len());
+
+        for warning in &self.warnings
+        {
+            out += &format!("WARNING: {}\n", warning);
+        }
+
+        out
+    }
+
+    // json
+    // the report as json for ci
+    pub fn json(&self) -> String
+    {
+        let regions: Vec<String> = self.regions.iter().map(|r| json::object(&[
+            ("start", r.start.to_string()),
+            ("end", (r.end - 1).to_string()),
+            ("size", r.size().to_string()),
+            ("free_after", r.free_after.to_string()),
+        ])).collect();
+
+        let warnings: Vec<String> = self.warnings.iter().map(|w| json::string(w)).collect();
+
+        json::object(&[
+            ("regions", json::array(&regions)),
+            ("used", self.used().to_string()),
+            ("warnings", json::array(&warnings)),
+        ]) + "\n"
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // object_code
+    // an object with a segment for each start and length
+    fn object_code(segments: &[(u32, usize)]) -> ObjectCode
+    {
+        let mut object_code = ObjectCode::new();
+
+        for (start, length) in segments
+        {
+            object_code.write(*start, &vec![0xea; *length]);
+        }
+
+        object_code
+    }
+
+    #[test]
+    fn regions_are_sorted_with_the_free_space_after()
+    {
+        let map = MemoryMap::new(&object_code(&[(0xc000, 0x100), (0x0800, 0x10)]), &[]);
+
+        assert_eq!(map.regions, vec![
+            Region { start: 0x0800, end: 0x0810, free_after: 0xc000 - 0x0810 },
+            Region { start: 0xc000, end: 0xc100, free_after: 0x10000 - 0xc100 },
+        ]);
+        assert_eq!(map.used(), 0x110);
+        assert!(map.warnings.is_empty());
+    }
+
+    #[test]
+    fn overlapping_regions_are_warned_about()
+    {
+        let map = MemoryMap::new(&object_code(&[(0x1000, 0x20), (0x1010, 0x10)]), &[]);
+
+        assert_eq!(map.regions[0].free_after, 0);
+        assert_eq!(map.warnings, vec!["$1000-$101F overlaps the region starting at $1010"]);
+    }
+
+    #[test]
+    fn spilling_into_io_or_the_vectors_is_warned_about()
+    {
+        let map = MemoryMap::new(&object_code(&[(0xcff0, 0x20), (0xfff0, 0x10)]), &[(0xd000, 0xdfff)]);
+
+        assert_eq!(map.warnings, vec![
+            "$CFF0-$D00F spills into I/O space at $D000-$DFFF",
+            "$FFF0-$FFFF spills into the vectors at $FFFA-$FFFF",
+        ]);
+    }
+
+    #[test]
+    fn starting_at_the_vectors_is_not_a_spill()
+    {
+        let map = MemoryMap::new(&object_code(&[(0xd000, 0x10), (0xfffa, 6)]), &[(0xd000, 0xdfff)]);
+
+        assert!(map.warnings.is_empty());
+    }
+
+    #[test]
+    fn json_report()
+    {
+        let map = MemoryMap::new(&object_code(&[(0xfff0, 0x10)]), &[]);
+
+        assert_eq!(map.json(), "{\"regions\":[{\"start\":65520,\"end\":65535,\"size\":16,\"free_after\":0}],\"used\":16,\"warnings\":[\"$FFF0-$FFFF spills into the vectors at $FFFA-$FFFF\"]}\n");
+    }
+}
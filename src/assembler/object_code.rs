@@ -0,0 +1,139 @@
+use crate::assembler::output_format::InesHeader;
+
+// Segment
+// a run of bytes that were emitted
+// one after another starting at an address
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Segment
+{
+    pub start: u32,
+    pub bytes: Vec<u8>,
+}
+
+impl Segment
+{
+    // end
+    // returns the address one past
+    // the last byte in the segment
+    pub fn end(&self) -> u32
+    {
+        self.start + self.bytes.len() as u32
+    }
+}
+
+
+// ObjectCode
+// holds everything the assembler emitted
+// tagged with the address it was emitted at,
+// the output formats are written from this
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Default)]
+pub struct ObjectCode
+{
+    pub segments: Vec<Segment>,
+    pub start_address: Option<u32>,     // set with .START, where execution begins
+    pub module_name: String,            // names the output in formats with a header
+    pub ines: InesHeader,               // set with the .INES directives
+}
+
+impl ObjectCode
+{
+    // new
+    // returns an empty object code
+    pub fn new() -> ObjectCode
+    {
+        ObjectCode { segments: vec![], start_address: None, module_name: "".to_string(), ines: InesHeader::default() }
+    }
+
+    // write
+    // appends bytes at the given address
+    // if the address doesn't follow the last segment
+    // (i.e. there was an .ORG) a new segment is started
+    pub fn write(&mut self, address: u32, bytes: &[u8])
+    {
+        if bytes.is_empty()
+        {
+            return;
+        }
+
+        if let Some(last) = self.segments.last_mut()
+        {
+            if last.end() == address
+            {
+                last.bytes.extend_from_slice(bytes);
+                return;
+            }
+        }
+
+        self.segments.push(Segment { start: address, bytes: bytes.to_vec() });
+    }
+
+    // contains
+    // true if a byte was emitted at the address
+    pub fn contains(&self, address: u32) -> bool
+    {
+        self.segments.iter().any(|s| address >= s.start && address < s.end())
+    }
+
+    // is_empty
+    // true if nothing was emitted
+    pub fn is_empty(&self) -> bool
+    {
+        self.segments.is_empty()
+    }
+
+    // lowest_address
+    // the lowest address anything was emitted at
+    pub fn lowest_address(&self) -> Option<u32>
+    {
+        self.segments.iter().map(|s| s.start).min()
+    }
+
+    // highest_address
+    // the address one past the highest emitted byte
+    pub fn highest_address(&self) -> Option<u32>
+    {
+        self.segments.iter().map(|s| s.end()).max()
+    }
+
+    // entry_point
+    // the .START address if there is one
+    // otherwise the lowest emitted address
+    pub fn entry_point(&self) -> u32
+    {
+        self.start_address.or(self.lowest_address()).unwrap_or(0)
+    }
+
+    // image
+    // a flat memory image from start up to (not including) end,
+    // the gaps between segments are filled with the fill byte
+    pub fn image(&self, start: u32, end: u32, fill: u8) -> Vec<u8>
+    {
+        let mut image = vec![fill; end.saturating_sub(start) as usize];
+
+        for segment in &self.segments
+        {
+            for (i, b) in segment.bytes.iter().enumerate()
+            {
+                let address = segment.start + i as u32;
+                if address >= start && address < end
+                {
+                    image[(address - start) as usize] = *b;
+                }
+            }
+        }
+
+        image
+    }
+
+    // flatten
+    // every emitted byte in the order it was emitted,
+    // this is what the raw binary output has always been
+    pub fn flatten(&self) -> Vec<u8>
+    {
+        self.segments.iter().flat_map(|s| s.bytes.iter().copied()).collect()
+    }
+}
@@ -0,0 +1,93 @@
+
+use crate::assembler::output_format::OutputFormat;
+use crate::assembler::symbol_file::SymbolFormat;
+use crate::assembler::warning::{Warning, WarningLevel};
+use crate::assembler::cpu::Cpu;
+
+
+// MessageFormat
+// how errors and warnings are written out
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Default)]
+pub enum MessageFormat
+{
+    #[default]
+    Human,  // the source line with a caret under the problem
+    Json,   // one json object per line
+}
+
+// AssemblerOptions
+// everything that changes how an
+// assembly is run, set from the cli or
+// by whoever is using the library
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct AssemblerOptions
+{
+    pub output_format: OutputFormat,
+    pub cpu: Cpu,                       // the cpu until a .CPU directive says otherwise
+    pub instruction_file: Option<String>, // an instruction table with more instructions
+    pub module_name: Option<String>,    // S0 header name, defaults to the input file name
+    pub chr_file: Option<String>,       // binary file put in CHR-ROM for NES images
+    pub listing_file: Option<String>,   // where to write the listing, if anywhere
+    pub symbol_files: Vec<(SymbolFormat, String)>,
+    pub map_file: Option<String>,       // memory map report as text
+    pub map_json_file: Option<String>,  // memory map report as json
+    pub io_ranges: Vec<(u32, u32)>,     // I/O space (inclusive) the memory map warns about
+    pub debug_info_file: Option<String>,
+    pub max_errors: usize,              // stop after this many errors, 0 never stops
+    pub color: bool,                    // ansi colors in rendered diagnostics
+    pub message_format: MessageFormat,
+    pub warnings: Vec<(Warning, WarningLevel)>, // changes to the default warning levels, later ones win
+    pub warnings_as_errors: bool,       // -Werror, every warning that is on is an error
+}
+
+impl Default for AssemblerOptions
+{
+    fn default() -> AssemblerOptions
+    {
+        AssemblerOptions
+        {
+            output_format: OutputFormat::default(),
+            cpu: Cpu::default(),
+            instruction_file: None,
+            module_name: None,
+            chr_file: None,
+            listing_file: None,
+            symbol_files: vec![],
+            map_file: None,
+            map_json_file: None,
+            io_ranges: vec![],
+            debug_info_file: None,
+            max_errors: 20,
+            color: false,
+            message_format: MessageFormat::default(),
+            warnings: vec![],
+            warnings_as_errors: false,
+        }
+    }
+}
+
+impl AssemblerOptions
+{
+    // warning_level
+    // what to do with the given warning
+    // after the command line had its say
+    pub fn warning_level(&self, warning: Warning) -> WarningLevel
+    {
+        let level = self.warnings.iter().rev()
+            .find(|(w, _)| *w == warning)
+            .map(|(_, level)| *level)
+            .unwrap_or(warning.default_level());
+
+        if self.warnings_as_errors && level == WarningLevel::Warn
+        {
+            return WarningLevel::Deny;
+        }
+
+        level
+    }
+}
@@ -0,0 +1,654 @@
+
+use crate::assembler::diagnostic::{Diagnostic, codes};
+use crate::assembler::object_code::ObjectCode;
+
+
+// OutputFormat
+// the formats the object code
+// can be written out as
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+#[derive(Default)]
+pub enum OutputFormat
+{
+    #[default]
+    Binary,                                                     // raw bytes, no header, the original output
+    IntelHex { record_length: u8, addressing: HexAddressing },
+    SRecord { record_length: u8 },                              // motorola S19, 16 bit addresses
+    Prg { basic_stub: Option<u16> },                            // commodore, the stub is the BASIC start address
+    Ines { nes2: bool },                                        // NES rom image, nes2 writes a NES 2.0 header
+    AppleBinary,                                                // DOS 3.3 B file, load address and length header
+    AppleSingle,                                                // AppleSingle with a ProDOS BIN file type
+}
+
+// InesHeader
+// what goes in the 16 byte header of a NES rom,
+// filled in by .INESPRG .INESCHR .INESMAP and .INESMIR
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct InesHeader
+{
+    pub prg_banks: u8,              // 16K PRG-ROM banks
+    pub chr_banks: Option<u8>,      // 8K CHR-ROM banks, worked out from the chr data if not given
+    pub mapper: u16,
+    pub mirroring: u8,              // flags 6 low nibble, bit 0 vertical, bit 1 battery, bit 3 four screen
+    pub chr_data: Vec<u8>,
+}
+
+impl Default for InesHeader
+{
+    fn default() -> InesHeader
+    {
+        InesHeader { prg_banks: 1, chr_banks: None, mapper: 0, mirroring: 0, chr_data: vec![] }
+    }
+}
+
+// HexAddressing
+// which record intel hex uses to
+// reach addresses above $FFFF
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum HexAddressing
+{
+    Linear,         // type 04 records, upper 16 bits of a 32 bit address
+    Segment,        // type 02 records, 8086 style segment * 16
+}
+
+impl OutputFormat
+{
+    // from_name
+    // turns a name given on the command line
+    // into an output format with its default settings
+    pub fn from_name(name: &str) -> Option<OutputFormat>
+    {
+        match name.to_lowercase().as_str()
+        {
+            "bin" | "binary" | "raw" => Some(OutputFormat::Binary),
+            "hex" | "ihex" | "intelhex" => Some(OutputFormat::IntelHex { record_length: 16, addressing: HexAddressing::Linear }),
+            "srec" | "s19" | "s-record" => Some(OutputFormat::SRecord { record_length: 16 }),
+            "prg" => Some(OutputFormat::Prg { basic_stub: None }),
+            "nes" | "ines" => Some(OutputFormat::Ines { nes2: false }),
+            "nes2" => Some(OutputFormat::Ines { nes2: true }),
+            "apple" | "dos33" => Some(OutputFormat::AppleBinary),
+            "applesingle" | "as" => Some(OutputFormat::AppleSingle),
+            _ => None,
+        }
+    }
+
+    // render
+    // turns the object code into the bytes
+    // that get written to the output file
+    pub fn render(&self, object_code: &ObjectCode) -> Result<Vec<u8>, Diagnostic>
+    {
+        match self
+        {
+            OutputFormat::Binary => Ok(object_code.flatten()),
+            OutputFormat::IntelHex { record_length, addressing } => Ok(intel_hex(object_code, *record_length, *addressing)?.into_bytes()),
+            OutputFormat::SRecord { record_length } => Ok(s_record(object_code, *record_length)?.into_bytes()),
+            OutputFormat::Prg { basic_stub } => prg(object_code, *basic_stub),
+            OutputFormat::Ines { nes2 } => ines(object_code, *nes2),
+            OutputFormat::AppleBinary => apple_binary(object_code),
+            OutputFormat::AppleSingle => apple_single(object_code),
+        }
+    }
+}
+
+
+// intel_hex
+// writes the object code as intel hex records,
+// data records never cross a 64K boundary so the
+// extended address records stay correct
+fn intel_hex(object_code: &ObjectCode, record_length: u8, addressing: HexAddressing) -> Result<String, Diagnostic>
+{
+    if record_length == 0
+    {
+        return Err(error("Intel HEX record length must be between 1 and 255"));
+    }
+
+    let mut out = String::new();
+    let mut upper: u32 = 0;     // the upper address bits the last extended record set
+
+    for segment in &object_code.segments
+    {
+        let mut address = segment.start;
+        let mut rest = segment.bytes.as_slice();
+
+        while !rest.is_empty()
+        {
+            if address >> 16 != upper
+            {
+                upper = address >> 16;
+                match addressing
+                {
+                    HexAddressing::Linear =>
+                    {
+                        out += &hex_record(0, 0x04, &[(upper >> 8) as u8, upper as u8]);
+                    }
+                    HexAddressing::Segment =>
+                    {
+                        if upper > 0xf
+                        {
+                            return Err(error(&format!("Address ${:X} is out of range for segment addressing", address)));
+                        }
+                        let segment_base = upper << 12;
+                        out += &hex_record(0, 0x02, &[(segment_base >> 8) as u8, segment_base as u8]);
+                    }
+                }
+            }
+
+            // how much fits before the record length or the 64K boundary
+            let to_boundary = 0x10000 - (address & 0xffff) as usize;
+            let count = rest.len().min(record_length as usize).min(to_boundary);
+
+            out += &hex_record(address as u16, 0x00, &rest[..count]);
+
+            address += count as u32;
+            rest = &rest[count..];
+        }
+    }
+
+    out += &hex_record(0, 0x01, &[]);
+
+    Ok(out)
+}
+
+// hex_record
+// formats one intel hex record
+// including its checksum
+fn hex_record(address: u16, record_type: u8, data: &[u8]) -> String
+{
+    let mut record = vec![data.len() as u8, (address >> 8) as u8, address as u8, record_type];
+    record.extend_from_slice(data);
+
+    let sum = record.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    record.push(sum.wrapping_neg());
+
+    let mut line = ":".to_string();
+    for b in record
+    {
+        line += &format!("{:02X}", b);
+    }
+    line + "\n"
+}
+
+// s_record
+// writes the object code as motorola S19,
+// an S0 header with the module name, S1 data records,
+// an S5 record count and an S9 start address
+fn s_record(object_code: &ObjectCode, record_length: u8) -> Result<String, Diagnostic>
+{
+    if record_length == 0 || record_length > 252
+    {
+        return Err(error("S-record length must be between 1 and 252"));
+    }
+
+    // the count byte covers the address and checksum
+    // too, so the name can only be 252 bytes long
+    let module_name = object_code.module_name.as_bytes();
+    let mut out = s_record_line(0, &[0, 0], &module_name[..module_name.len().min(252)]);
+    let mut count: u32 = 0;
+
+    for segment in &object_code.segments
+    {
+        if segment.end() > 0x10000
+        {
+            return Err(error(&format!("Address ${:X} doesn't fit in an S1 record", segment.end() - 1)));
+        }
+
+        for (i, chunk) in segment.bytes.chunks(record_length as usize).enumerate()
+        {
+            let address = segment.start + (i * record_length as usize) as u32;
+            out += &s_record_line(1, &[(address >> 8) as u8, address as u8], chunk);
+            count += 1;
+        }
+    }
+
+    // S5 holds a 16 bit count, S6 is there for anything bigger
+    if count <= 0xffff
+    {
+        out += &s_record_line(5, &[(count >> 8) as u8, count as u8], &[]);
+    }
+    else
+    {
+        out += &s_record_line(6, &[(count >> 16) as u8, (count >> 8) as u8, count as u8], &[]);
+    }
+
+    let start = object_code.entry_point();
+    if start > 0xffff
+    {
+        return Err(error(&format!("Start address ${:X} doesn't fit in an S9 record", start)));
+    }
+    out += &s_record_line(9, &[(start >> 8) as u8, start as u8], &[]);
+
+    Ok(out)
+}
+
+// s_record_line
+// formats one S-record, the count covers the
+// address, data and checksum bytes
+fn s_record_line(record_type: u8, address: &[u8], data: &[u8]) -> String
+{
+    let mut record = vec![(address.len() + data.len() + 1) as u8];
+    record.extend_from_slice(address);
+    record.extend_from_slice(data);
+
+    let sum = record.iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    record.push(!sum);
+
+    let mut line = format!("S{}", record_type);
+    for b in record
+    {
+        line += &format!("{:02X}", b);
+    }
+    line + "\n"
+}
+
+// prg
+// writes a commodore program file, the two byte
+// load address followed by a memory image, optionally
+// starting with a BASIC line that SYS's into the code
+fn prg(object_code: &ObjectCode, basic_stub: Option<u16>) -> Result<Vec<u8>, Diagnostic>
+{
+    let (low, high) = match (object_code.lowest_address(), object_code.highest_address())
+    {
+        (Some(low), Some(high)) => (low, high),
+        _ => (basic_stub.map(|b| b as u32).unwrap_or(0), basic_stub.map(|b| b as u32).unwrap_or(0)),
+    };
+
+    if high > 0x10000
+    {
+        return Err(error(&format!("Address ${:X} doesn't fit in a PRG file", high - 1)));
+    }
+
+    let mut out: Vec<u8> = vec![];
+
+    match basic_stub
+    {
+        None =>
+        {
+            out.extend_from_slice(&[low as u8, (low >> 8) as u8]);
+            out.extend(object_code.image(low, high, 0));
+        }
+        Some(basic_start) =>
+        {
+            let stub = sys_stub(basic_start, object_code.entry_point());
+            let code_start = basic_start as u32 + stub.len() as u32;
+
+            if object_code.lowest_address().is_some() && low < code_start
+            {
+                return Err(error(&format!("Code at ${:04X} overlaps the BASIC stub, it should start at ${:04X} or later", low, code_start)));
+            }
+
+            out.extend_from_slice(&[basic_start as u8, (basic_start >> 8) as u8]);
+            out.extend(stub);
+            out.extend(object_code.image(code_start, high.max(code_start), 0));
+        }
+    }
+
+    Ok(out)
+}
+
+// sys_stub
+// the tokenized BASIC program "10 SYS <entry>"
+// as it sits in memory at basic_start
+fn sys_stub(basic_start: u16, entry: u32) -> Vec<u8>
+{
+    let digits = entry.to_string();
+
+    // link to the next line, which is the end of program marker
+    let next_line = basic_start as u32 + 6 + digits.len() as u32;
+
+    let mut stub = vec![next_line as u8, (next_line >> 8) as u8, 10, 0, 0x9e];
+    stub.extend_from_slice(digits.as_bytes());
+    stub.extend_from_slice(&[0, 0, 0]);
+    stub
+}
+
+// ines
+// writes a NES rom image, the header then the PRG-ROM
+// banks (which end at $FFFF) padded with $FF, then the CHR-ROM
+fn ines(object_code: &ObjectCode, nes2: bool) -> Result<Vec<u8>, Diagnostic>
+{
+    let header = &object_code.ines;
+
+    if header.prg_banks == 0 || header.prg_banks > 2
+    {
+        return Err(error("Only 1 or 2 PRG-ROM banks can be addressed without bank switching, set .INESPRG to 1 or 2"));
+    }
+    if header.mirroring & 0x04 != 0
+    {
+        return Err(error("Trainers aren't supported, bit 2 of .INESMIR must be clear"));
+    }
+    if header.mirroring > 0x0f
+    {
+        return Err(error(".INESMIR only uses the low 4 bits of flags 6"));
+    }
+    if (!nes2 && header.mapper > 0xff) || header.mapper > 0xfff
+    {
+        return Err(error(&format!("Mapper {} doesn't fit in the header", header.mapper)));
+    }
+
+    // PRG-ROM sits at the top of the address space
+    let prg_start = 0x10000 - header.prg_banks as u32 * 0x4000;
+
+    for segment in &object_code.segments
+    {
+        if segment.start < prg_start || segment.end() > 0x10000
+        {
+            return Err(error(&format!("Code at ${:04X}-${:04X} is outside PRG-ROM (${:04X}-$FFFF)", segment.start, segment.end() - 1, prg_start)));
+        }
+    }
+
+    // NMI, RESET and IRQ
+    for (vector, name) in [(0xfffa, "NMI"), (0xfffc, "RESET"), (0xfffe, "IRQ")]
+    {
+        if !object_code.contains(vector) || !object_code.contains(vector + 1)
+        {
+            return Err(error(&format!("The {} vector at ${:04X} was never set, use .ORG ${:04X} and .BYTE to set it", name, vector, vector)));
+        }
+    }
+
+    let chr_banks = match header.chr_banks
+    {
+        Some(n) => n as usize,
+        None => header.chr_data.len().div_ceil(0x2000),
+    };
+
+    if header.chr_data.len() > chr_banks * 0x2000
+    {
+        return Err(error(&format!("{} bytes of CHR data don't fit in {} CHR-ROM banks", header.chr_data.len(), chr_banks)));
+    }
+    if chr_banks > 0xff
+    {
+        return Err(error("Too much CHR data for the header"));
+    }
+
+    let mut flags7 = (header.mapper & 0xf0) as u8;
+    let mut byte8 = 0;
+    if nes2
+    {
+        flags7 |= 0x08;
+        byte8 = (header.mapper >> 8) as u8 & 0x0f;
+    }
+
+    let mut out = vec![b'N', b'E', b'S', 0x1a, header.prg_banks, chr_banks as u8, ((header.mapper & 0x0f) << 4) as u8 | header.mirroring, flags7, byte8];
+    out.resize(16, 0);
+
+    out.extend(object_code.image(prg_start, 0x10000, 0xff));
+
+    let mut chr = header.chr_data.clone();
+    chr.resize(chr_banks * 0x2000, 0);
+    out.extend(chr);
+
+    Ok(out)
+}
+
+// apple_range
+// the load address and memory image
+// for the apple II formats, which can't go past $FFFF
+fn apple_range(object_code: &ObjectCode) -> Result<(u16, Vec<u8>), Diagnostic>
+{
+    let low = object_code.lowest_address().unwrap_or(0);
+    let high = object_code.highest_address().unwrap_or(0);
+
+    if high > 0x10000
+    {
+        return Err(error(&format!("Address ${:X} doesn't fit in an Apple II binary", high - 1)));
+    }
+
+    Ok((low as u16, object_code.image(low, high, 0)))
+}
+
+// apple_binary
+// writes a DOS 3.3 binary (B) file, the load
+// address and length both little endian then the image
+fn apple_binary(object_code: &ObjectCode) -> Result<Vec<u8>, Diagnostic>
+{
+    let (load_address, image) = apple_range(object_code)?;
+    let length = image.len() as u16;
+
+    let mut out = vec![load_address as u8, (load_address >> 8) as u8, length as u8, (length >> 8) as u8];
+    out.extend(image);
+
+    Ok(out)
+}
+
+// apple_single
+// writes an AppleSingle (version 2) file with the real name,
+// ProDOS file info (BIN, aux type is the load address) and
+// the image as the data fork, everything is big endian
+fn apple_single(object_code: &ObjectCode) -> Result<Vec<u8>, Diagnostic>
+{
+    let (load_address, image) = apple_range(object_code)?;
+    let name = object_code.module_name.as_bytes();
+
+    // access $C3 (read, write, rename, destroy), file type $06 BIN, aux type
+    let mut prodos_info = vec![0x00, 0xc3, 0x00, 0x06];
+    prodos_info.extend_from_slice(&(load_address as u32).to_be_bytes());
+
+    // entry id and data, in the order they are written
+    let entries: Vec<(u32, &[u8])> = vec![(3, name), (11, &prodos_info), (1, &image)];
+
+    let mut out: Vec<u8> = vec![];
+    out.extend_from_slice(&0x0005_1600u32.to_be_bytes());        // magic
+    out.extend_from_slice(&0x0002_0000u32.to_be_bytes());        // version
+    out.extend_from_slice(&[0; 16]);                            // filler
+    out.extend_from_slice(&(entries.len() as u16).to_be_bytes());
+
+    // the data starts after the header and the entry descriptors
+    let mut offset = (26 + entries.len() * 12) as u32;
+    for (id, data) in &entries
+    {
+        out.extend_from_slice(&id.to_be_bytes());
+        out.extend_from_slice(&offset.to_be_bytes());
+        out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        offset += data.len() as u32;
+    }
+
+    for (_, data) in &entries
+    {
+        out.extend_from_slice(data);
+    }
+
+    Ok(out)
+}
+
+// error
+// just returns an error
+// with the from set to output
+fn error(mssg: &str) -> Diagnostic
+{
+    Diagnostic::new(codes::OUTPUT, mssg, "output")
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // object_code
+    // object code with the bytes written at each address
+    fn object_code(segments: &[(u32, &[u8])]) -> ObjectCode
+    {
+        let mut object_code = ObjectCode::new();
+        for (address, bytes) in segments
+        {
+            object_code.write(*address, bytes);
+        }
+        object_code
+    }
+
+    #[test]
+    fn intel_hex_records_and_checksums()
+    {
+        let hex = intel_hex(&object_code(&[(0x0030, &[0x02, 0x33, 0x7a])]), 16, HexAddressing::Linear).unwrap();
+        assert_eq!(hex, ":0300300002337A1E\n:00000001FF\n");
+    }
+
+    #[test]
+    fn intel_hex_above_64k()
+    {
+        let hex = intel_hex(&object_code(&[(0x12345, &[0xaa])]), 16, HexAddressing::Linear).unwrap();
+        assert_eq!(hex, ":020000040001F9\n:01234500AAED\n:00000001FF\n");
+
+        let hex = intel_hex(&object_code(&[(0x12345, &[0xaa])]), 16, HexAddressing::Segment).unwrap();
+        assert_eq!(hex, ":020000021000EC\n:01234500AAED\n:00000001FF\n");
+    }
+
+    #[test]
+    fn intel_hex_records_split_at_the_length_and_64k()
+    {
+        let hex = intel_hex(&object_code(&[(0xfffe, &[1, 2, 3, 4])]), 3, HexAddressing::Linear).unwrap();
+        let lines: Vec<&str> = hex.lines().collect();
+        assert_eq!(lines, vec![":02FFFE000102FE", ":020000040001F9", ":020000000304F7", ":00000001FF"]);
+    }
+
+    #[test]
+    fn s_records_and_checksums()
+    {
+        let data = [0x0a, 0x0a, 0x0d, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut code = object_code(&[(0x7af0, &data)]);
+        code.module_name = "HDR".to_string();
+
+        let srec = s_record(&code, 16).unwrap();
+        assert_eq!(srec, "S00600004844521B\nS1137AF00A0A0D0000000000000000000000000061\nS5030001FB\nS9037AF092\n");
+    }
+
+    #[test]
+    fn s9_is_the_start_address()
+    {
+        let mut code = object_code(&[(0x0600, &[0xea]), (0x0610, &[0x60])]);
+        code.start_address = Some(0x0610);
+
+        let srec = s_record(&code, 16).unwrap();
+        assert_eq!(srec.lines().last(), Some("S9030610E6"));
+        assert!(srec.contains("S5030002FA\n"), "{}", srec);
+    }
+
+    #[test]
+    fn s9_start_past_64k_is_an_error()
+    {
+        let mut code = object_code(&[(0x0600, &[0xea])]);
+        code.start_address = Some(0x12345);
+        assert_eq!(s_record(&code, 16).unwrap_err().code, codes::OUTPUT);
+    }
+
+    #[test]
+    fn long_module_names_are_cut_to_fit_the_s0_record()
+    {
+        let mut code = object_code(&[(0x0600, &[0xea])]);
+        code.module_name = "A".repeat(300);
+
+        let srec = s_record(&code, 16).unwrap();
+        let header = srec.lines().next().unwrap();
+        assert!(header.starts_with("S0FF0000"), "{}", header);
+        assert_eq!(header.len(), 4 + 255 * 2);
+    }
+
+    #[test]
+    fn prg_is_the_load_address_then_the_image()
+    {
+        let bytes = prg(&object_code(&[(0xc000, &[0xa9, 0x01]), (0xc004, &[0x60])]), None).unwrap();
+        assert_eq!(bytes, vec![0x00, 0xc0, 0xa9, 0x01, 0x00, 0x00, 0x60]);
+    }
+
+    #[test]
+    fn prg_sys_stub()
+    {
+        // 10 SYS 2061 at $0801, the usual C64 stub
+        let bytes = prg(&object_code(&[(0x080d, &[0x60])]), Some(0x0801)).unwrap();
+        assert_eq!(bytes, vec![0x01, 0x08, 0x0b, 0x08, 0x0a, 0x00, 0x9e, b'2', b'0', b'6', b'1', 0x00, 0x00, 0x00, 0x60]);
+
+        let overlapping = prg(&object_code(&[(0x0808, &[0x60])]), Some(0x0801));
+        assert_eq!(overlapping.unwrap_err().code, codes::OUTPUT);
+    }
+
+    // nes_code
+    // a one bank rom with an RTI and the vectors set
+    fn nes_code() -> ObjectCode
+    {
+        object_code(&[(0xc000, &[0x40]), (0xfffa, &[0x00, 0xc0, 0x00, 0xc0, 0x00, 0xc0])])
+    }
+
+    #[test]
+    fn ines_header()
+    {
+        let mut code = nes_code();
+        code.ines.mapper = 0x21;
+        code.ines.mirroring = 0x01;
+        code.ines.chr_data = vec![0x55; 100];
+
+        let rom = ines(&code, false).unwrap();
+        assert_eq!(&rom[..16], &[b'N', b'E', b'S', 0x1a, 1, 1, 0x11, 0x20, 0, 0, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(rom.len(), 16 + 0x4000 + 0x2000);
+
+        // PRG-ROM is $C000-$FFFF padded with $FF, CHR-ROM padded with 0
+        assert_eq!(rom[16], 0x40);
+        assert_eq!(rom[17], 0xff);
+        assert_eq!(&rom[16 + 0x3ffa..16 + 0x4000], &[0x00, 0xc0, 0x00, 0xc0, 0x00, 0xc0]);
+        assert_eq!(rom[16 + 0x4000 + 99], 0x55);
+        assert_eq!(rom[16 + 0x4000 + 100], 0x00);
+    }
+
+    #[test]
+    fn nes2_header()
+    {
+        let mut code = nes_code();
+        code.ines.mapper = 0x123;
+
+        let rom = ines(&code, true).unwrap();
+        assert_eq!(&rom[..9], &[b'N', b'E', b'S', 0x1a, 1, 0, 0x30, 0x28, 0x01]);
+        assert_eq!(rom.len(), 16 + 0x4000);
+
+        assert_eq!(ines(&code, false).unwrap_err().code, codes::OUTPUT);
+    }
+
+    #[test]
+    fn ines_needs_the_vectors()
+    {
+        let code = object_code(&[(0xc000, &[0x40])]);
+        let err = ines(&code, false).unwrap_err();
+        assert_eq!(err.code, codes::OUTPUT);
+        assert!(err.message.contains("NMI"));
+    }
+
+    #[test]
+    fn apple_binary_header()
+    {
+        let bytes = apple_binary(&object_code(&[(0x0300, &[0xa9, 0x01]), (0x0303, &[0x60])])).unwrap();
+        assert_eq!(bytes, vec![0x00, 0x03, 0x04, 0x00, 0xa9, 0x01, 0x00, 0x60]);
+    }
+
+    #[test]
+    fn apple_single_layout()
+    {
+        let mut code = object_code(&[(0x0300, &[0x60])]);
+        code.module_name = "HELLO".to_string();
+        let bytes = apple_single(&code).unwrap();
+
+        let mut expected: Vec<u8> = vec![0x00, 0x05, 0x16, 0x00, 0x00, 0x02, 0x00, 0x00];
+        expected.extend([0; 16]);
+        expected.extend([0x00, 0x03]);
+        expected.extend([0, 0, 0, 3, 0, 0, 0, 62, 0, 0, 0, 5]);     // real name
+        expected.extend([0, 0, 0, 11, 0, 0, 0, 67, 0, 0, 0, 8]);    // prodos file info
+        expected.extend([0, 0, 0, 1, 0, 0, 0, 75, 0, 0, 0, 1]);     // data fork
+        expected.extend(b"HELLO");
+        expected.extend([0x00, 0xc3, 0x00, 0x06, 0x00, 0x00, 0x03, 0x00]);
+        expected.push(0x60);
+
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn apple_formats_stop_at_64k()
+    {
+        let code = object_code(&[(0xffff, &[0x60, 0x60])]);
+        assert_eq!(apple_binary(&code).unwrap_err().code, codes::OUTPUT);
+        assert_eq!(apple_single(&code).unwrap_err().code, codes::OUTPUT);
+    }
+}
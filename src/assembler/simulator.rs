@@ -0,0 +1,709 @@
+use std::fmt;
+use crate::assembler::cpu::Cpu;
+use crate::assembler::instruction::{AddressingMode, Instruction, Opcode};
+use crate::assembler::diagnostic::{Diagnostic, codes};
+
+
+// the status register bits
+pub const CARRY: u8 = 0x01;
+pub const ZERO: u8 = 0x02;
+pub const INTERRUPT: u8 = 0x04;
+pub const DECIMAL: u8 = 0x08;
+pub const BREAK: u8 = 0x10;
+pub const UNUSED: u8 = 0x20;
+pub const OVERFLOW: u8 = 0x40;
+pub const NEGATIVE: u8 = 0x80;
+
+// the instructions that take a cycle more when
+// indexing crosses a page, the stores and read modify
+// writes always take it and have it in their base count
+const PAGE_CROSS_READS: [&str; 9] = ["adc", "and", "cmp", "eor", "lda", "ldx", "ldy", "ora", "sbc"];
+
+
+// Registers
+// what the cpu holds, status
+// is made of the bits above
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Default)]
+pub struct Registers
+{
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub pc: u16,
+    pub status: u8,
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // set flags upper case, clear ones lower case
+        let flags: String = "NV-BDIZC".chars().enumerate()
+            .map(|(i, c)| if self.status & (0x80 >> i) != 0 { c } else { c.to_ascii_lowercase() })
+            .collect();
+        write!(f, "PC=${:04X} A=${:02X} X=${:02X} Y=${:02X} SP=${:02X} P=${:02X} {}", self.pc, self.a, self.x, self.y, self.sp, self.status, flags)
+    }
+}
+
+
+// StopReason
+// why the simulator stopped running
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum StopReason
+{
+    Break,              // got to a BRK, it isn't run
+    Address,            // got to the address it was told to stop at
+    Returned,           // the RTS of a call came back
+    CycleLimit,         // ran for as many cycles as it was allowed
+    UnknownOpcode(u8),  // a byte that isn't a 6502 instruction
+}
+
+impl fmt::Display for StopReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StopReason::Break => write!(f, "BRK"),
+            StopReason::Address => write!(f, "the stop address"),
+            StopReason::Returned => write!(f, "the return"),
+            StopReason::CycleLimit => write!(f, "the cycle limit"),
+            StopReason::UnknownOpcode(opcode) => write!(f, "unknown opcode ${:02X}", opcode),
+        }
+    }
+}
+
+
+// Simulator
+// an NMOS 6502 with 64K of ram, decoded with
+// the instruction table and counting its cycles
+pub struct Simulator
+{
+    pub registers: Registers,
+    pub memory: Vec<u8>,
+    pub cycles: u64,
+    pub decimal_mode: bool,             // false on the 2A03, SED does nothing to ADC and SBC
+    opcodes: Vec<Option<Opcode>>,       // indexed by the opcode
+}
+
+impl Simulator
+{
+    // new
+    // a simulator with cleared memory and the registers
+    // as they are after a reset, only the NMOS 6502
+    // instructions run so it is an error for other cpus
+    pub fn new(cpu: Cpu) -> Result<Simulator, Diagnostic>
+    {
+        if !matches!(cpu, Cpu::Mos6502 | Cpu::Ricoh2A03)
+        {
+            return Err(Diagnostic::new(codes::UNSUPPORTED_CPU, &format!("The simulator can only run the 6502 and the 2A03, not the {}", cpu), "Simulator"));
+        }
+
+        let mut opcodes: Vec<Option<Opcode>> = vec![None; 256];
+        for opcode in Instruction::opcodes(cpu, &[])
+        {
+            let index = opcode.opcode as usize;
+            opcodes[index] = Some(opcode);
+        }
+
+        Ok(Simulator
+        {
+            registers: Registers { sp: 0xfd, status: INTERRUPT | UNUSED, ..Registers::default() },
+            memory: vec![0; 0x10000],
+            cycles: 0,
+            decimal_mode: cpu.has_decimal_mode(),
+            opcodes,
+        })
+    }
+
+    // load
+    // copies bytes into memory from the address,
+    // anything past $FFFF wraps to the start
+    pub fn load(&mut self, address: u16, bytes: &[u8])
+    {
+        for (i, byte) in bytes.iter().enumerate()
+        {
+            self.memory[(address as usize + i) & 0xffff] = *byte;
+        }
+    }
+
+    // reset
+    // starts at the reset vector the way the cpu does
+    pub fn reset(&mut self)
+    {
+        self.registers.pc = self.read_word(0xfffc);
+        self.registers.sp = 0xfd;
+        self.registers.status |= INTERRUPT | UNUSED;
+        self.cycles += 7;
+    }
+
+    // run
+    // steps until a BRK, the until address or
+    // the cycle limit, whichever comes first
+    pub fn run(&mut self, until: Option<u16>, max_cycles: u64) -> StopReason
+    {
+        loop
+        {
+            if until == Some(self.registers.pc)
+            {
+                return StopReason::Address;
+            }
+            if self.cycles >= max_cycles
+            {
+                return StopReason::CycleLimit;
+            }
+            if let Err(reason) = self.step()
+            {
+                return reason;
+            }
+        }
+    }
+
+    // call
+    // JSRs to the address from wherever pc is and runs until
+    // the RTS that comes back to it, a BRK or max_cycles more cycles
+    pub fn call(&mut self, address: u16, max_cycles: u64) -> StopReason
+    {
+        let back = self.registers.pc;
+        let sp = self.registers.sp;
+        let limit = self.cycles + max_cycles;
+
+        self.push((back.wrapping_sub(1) >> 8) as u8);
+        self.push(back.wrapping_sub(1) as u8);
+        self.registers.pc = address;
+        self.cycles += 6;
+
+        loop
+        {
+            if self.registers.pc == back && self.registers.sp == sp
+            {
+                return StopReason::Returned;
+            }
+            if self.cycles >= limit
+            {
+                return StopReason::CycleLimit;
+            }
+            if let Err(reason) = self.step()
+            {
+                return reason;
+            }
+        }
+    }
+
+    // step
+    // runs the instruction at pc, a BRK or an
+    // unknown opcode stops it without running anything
+    pub fn step(&mut self) -> Result<(), StopReason>
+    {
+        let pc = self.registers.pc;
+        let byte = self.read(pc);
+
+        let opcode = match &self.opcodes[byte as usize]
+        {
+            Some(opcode) if opcode.mnemonic == "brk" => return Err(StopReason::Break),
+            Some(opcode) => opcode.clone(),
+            None => return Err(StopReason::UnknownOpcode(byte)),
+        };
+
+        let (address, page_crossed) = self.operand_address(opcode.mode, pc);
+
+        self.registers.pc = pc.wrapping_add(opcode.bytes as u16);
+        self.cycles += opcode.cycles as u64;
+        if page_crossed && PAGE_CROSS_READS.contains(&opcode.mnemonic.as_str())
+        {
+            self.cycles += 1;
+        }
+
+        self.execute(&opcode.mnemonic, opcode.mode, address);
+        Ok(())
+    }
+
+    // operand_address
+    // where the operand of the instruction at pc is,
+    // and if indexing it crossed into the next page
+    fn operand_address(&self, mode: AddressingMode, pc: u16) -> (u16, bool)
+    {
+        use AddressingMode::*;
+
+        let byte = self.read(pc.wrapping_add(1));
+        let word = self.read_word(pc.wrapping_add(1));
+        let indexed = |base: u16, index: u8| -> (u16, bool)
+        {
+            let address = base.wrapping_add(index as u16);
+            (address, address & 0xff00 != base & 0xff00)
+        };
+
+        match mode
+        {
+            Immediate | Relative => (pc.wrapping_add(1), false),
+            ZeroPage => (byte as u16, false),
+            ZeroPageX => (byte.wrapping_add(self.registers.x) as u16, false),
+            ZeroPageY => (byte.wrapping_add(self.registers.y) as u16, false),
+            Absolute => (word, false),
+            AbsoluteX => indexed(word, self.registers.x),
+            AbsoluteY => indexed(word, self.registers.y),
+            // the NMOS 6502 doesn't carry into the
+            // high byte of the pointer, JMP ($10FF) reads $10FF and $1000
+            Indirect => (self.read(word) as u16 | (self.read((word & 0xff00) | (word.wrapping_add(1) & 0xff)) as u16) << 8, false),
+            IndirectX => (self.read_zero_page_word(byte.wrapping_add(self.registers.x)), false),
+            IndirectY => indexed(self.read_zero_page_word(byte), self.registers.y),
+            _ => (0, false),
+        }
+    }
+
+    // execute
+    // does what the instruction does with
+    // its operand at the address
+    fn execute(&mut self, mnemonic: &str, mode: AddressingMode, address: u16)
+    {
+        let accumulator = mode == AddressingMode::Accumulator;
+
+        match mnemonic
+        {
+            "lda" => { self.registers.a = self.read(address); self.set_nz(self.registers.a); }
+            "ldx" => { self.registers.x = self.read(address); self.set_nz(self.registers.x); }
+            "ldy" => { self.registers.y = self.read(address); self.set_nz(self.registers.y); }
+            "sta" => self.write(address, self.registers.a),
+            "stx" => self.write(address, self.registers.x),
+            "sty" => self.write(address, self.registers.y),
+
+            "tax" => { self.registers.x = self.registers.a; self.set_nz(self.registers.x); }
+            "tay" => { self.registers.y = self.registers.a; self.set_nz(self.registers.y); }
+            "txa" => { self.registers.a = self.registers.x; self.set_nz(self.registers.a); }
+            "tya" => { self.registers.a = self.registers.y; self.set_nz(self.registers.a); }
+            "tsx" => { self.registers.x = self.registers.sp; self.set_nz(self.registers.x); }
+            "txs" => self.registers.sp = self.registers.x,
+
+            "pha" => self.push(self.registers.a),
+            "php" => self.push(self.registers.status | BREAK | UNUSED),
+            "pla" => { self.registers.a = self.pull(); self.set_nz(self.registers.a); }
+            "plp" => self.registers.status = (self.pull() & !BREAK) | UNUSED,
+
+            "and" => { self.registers.a &= self.read(address); self.set_nz(self.registers.a); }
+            "ora" => { self.registers.a |= self.read(address); self.set_nz(self.registers.a); }
+            "eor" => { self.registers.a ^= self.read(address); self.set_nz(self.registers.a); }
+            "adc" => self.add(self.read(address)),
+            "sbc" => self.subtract(self.read(address)),
+            "cmp" => self.compare(self.registers.a, self.read(address)),
+            "cpx" => self.compare(self.registers.x, self.read(address)),
+            "cpy" => self.compare(self.registers.y, self.read(address)),
+            "bit" =>
+            {
+                let value = self.read(address);
+                self.set_flag(ZERO, self.registers.a & value == 0);
+                self.set_flag(OVERFLOW, value & 0x40 != 0);
+                self.set_flag(NEGATIVE, value & 0x80 != 0);
+            }
+
+            "inc" | "dec" | "asl" | "lsr" | "rol" | "ror" =>
+            {
+                let value = if accumulator { self.registers.a } else { self.read(address) };
+                let carry = self.registers.status & CARRY;
+
+                let result = match mnemonic
+                {
+                    "inc" => value.wrapping_add(1),
+                    "dec" => value.wrapping_sub(1),
+                    "asl" => { self.set_flag(CARRY, value & 0x80 != 0); value << 1 }
+                    "lsr" => { self.set_flag(CARRY, value & 0x01 != 0); value >> 1 }
+                    "rol" => { self.set_flag(CARRY, value & 0x80 != 0); (value << 1) | carry }
+                    _ => { self.set_flag(CARRY, value & 0x01 != 0); (value >> 1) | (carry << 7) }
+                };
+
+                self.set_nz(result);
+                if accumulator
+                {
+                    self.registers.a = result;
+                }
+                else
+                {
+                    self.write(address, result);
+                }
+            }
+            "inx" => { self.registers.x = self.registers.x.wrapping_add(1); self.set_nz(self.registers.x); }
+            "iny" => { self.registers.y = self.registers.y.wrapping_add(1); self.set_nz(self.registers.y); }
+            "dex" => { self.registers.x = self.registers.x.wrapping_sub(1); self.set_nz(self.registers.x); }
+            "dey" => { self.registers.y = self.registers.y.wrapping_sub(1); self.set_nz(self.registers.y); }
+
+            "bcc" => self.branch(address, self.registers.status & CARRY == 0),
+            "bcs" => self.branch(address, self.registers.status & CARRY != 0),
+            "bne" => self.branch(address, self.registers.status & ZERO == 0),
+            "beq" => self.branch(address, self.registers.status & ZERO != 0),
+            "bpl" => self.branch(address, self.registers.status & NEGATIVE == 0),
+            "bmi" => self.branch(address, self.registers.status & NEGATIVE != 0),
+            "bvc" => self.branch(address, self.registers.status & OVERFLOW == 0),
+            "bvs" => self.branch(address, self.registers.status & OVERFLOW != 0),
+
+            "jmp" => self.registers.pc = address,
+            "jsr" =>
+            {
+                // the address pushed is the last byte of the jsr
+                let back = self.registers.pc.wrapping_sub(1);
+                self.push((back >> 8) as u8);
+                self.push(back as u8);
+                self.registers.pc = address;
+            }
+            "rts" =>
+            {
+                let back = self.pull() as u16 | (self.pull() as u16) << 8;
+                self.registers.pc = back.wrapping_add(1);
+            }
+            "rti" =>
+            {
+                self.registers.status = (self.pull() & !BREAK) | UNUSED;
+                self.registers.pc = self.pull() as u16 | (self.pull() as u16) << 8;
+            }
+
+            "clc" => self.set_flag(CARRY, false),
+            "sec" => self.set_flag(CARRY, true),
+            "cli" => self.set_flag(INTERRUPT, false),
+            "sei" => self.set_flag(INTERRUPT, true),
+            "cld" => self.set_flag(DECIMAL, false),
+            "sed" => self.set_flag(DECIMAL, true),
+            "clv" => self.set_flag(OVERFLOW, false),
+            _ => (),
+        }
+    }
+
+    // add
+    // ADC, in decimal mode the NMOS 6502 sets
+    // N and V from the half way result and Z
+    // from the binary sum
+    fn add(&mut self, value: u8)
+    {
+        let a = self.registers.a;
+        let carry = (self.registers.status & CARRY) as u16;
+        let binary = a as u16 + value as u16 + carry;
+
+        if self.decimal_mode && self.registers.status & DECIMAL != 0
+        {
+            let mut low = (a & 0x0f) as u16 + (value & 0x0f) as u16 + carry;
+            let mut high = (a & 0xf0) as u16 + (value & 0xf0) as u16;
+            if low > 0x09
+            {
+                low += 0x06;
+            }
+            if low > 0x0f
+            {
+                high += 0x10;
+            }
+
+            self.set_flag(ZERO, binary & 0xff == 0);
+            self.set_flag(NEGATIVE, high & 0x80 != 0);
+            self.set_flag(OVERFLOW, !(a ^ value) & (a ^ high as u8) & 0x80 != 0);
+
+            if high > 0x90
+            {
+                high += 0x60;
+            }
+            self.set_flag(CARRY, high > 0xff);
+            self.registers.a = (high as u8 & 0xf0) | (low as u8 & 0x0f);
+            return;
+        }
+
+        let result = binary as u8;
+        self.set_flag(CARRY, binary > 0xff);
+        self.set_flag(OVERFLOW, !(a ^ value) & (a ^ result) & 0x80 != 0);
+        self.registers.a = result;
+        self.set_nz(result);
+    }
+
+    // subtract
+    // SBC, in decimal mode the flags
+    // come from the binary difference
+    fn subtract(&mut self, value: u8)
+    {
+        let a = self.registers.a;
+        let borrow = 1 - (self.registers.status & CARRY) as i16;
+        let binary = a as i16 - value as i16 - borrow;
+        let result = binary as u8;
+
+        self.set_flag(CARRY, binary >= 0);
+        self.set_flag(OVERFLOW, (a ^ value) & (a ^ result) & 0x80 != 0);
+        self.set_nz(result);
+
+        if self.decimal_mode && self.registers.status & DECIMAL != 0
+        {
+            let mut low = (a & 0x0f) as i16 - (value & 0x0f) as i16 - borrow;
+            let mut high = (a & 0xf0) as i16 - (value & 0xf0) as i16;
+            if low < 0
+            {
+                low -= 0x06;
+                high -= 0x10;
+            }
+            if high < 0
+            {
+                high -= 0x60;
+            }
+            self.registers.a = (high as u8 & 0xf0) | (low as u8 & 0x0f);
+            return;
+        }
+
+        self.registers.a = result;
+    }
+
+    // compare
+    // CMP, CPX and CPY, a subtraction
+    // that only keeps the flags
+    fn compare(&mut self, register: u8, value: u8)
+    {
+        self.set_flag(CARRY, register >= value);
+        self.set_nz(register.wrapping_sub(value));
+    }
+
+    // branch
+    // a taken branch is a cycle more
+    // and another if it lands on another page
+    fn branch(&mut self, address: u16, taken: bool)
+    {
+        if !taken
+        {
+            return;
+        }
+
+        let offset = self.read(address) as i8;
+        let target = self.registers.pc.wrapping_add(offset as u16);
+
+        self.cycles += 1;
+        if target & 0xff00 != self.registers.pc & 0xff00
+        {
+            self.cycles += 1;
+        }
+        self.registers.pc = target;
+    }
+
+    // set_flag
+    // sets or clears a status bit
+    fn set_flag(&mut self, flag: u8, on: bool)
+    {
+        if on
+        {
+            self.registers.status |= flag;
+        }
+        else
+        {
+            self.registers.status &= !flag;
+        }
+    }
+
+    // set_nz
+    // the zero and negative flags for a result
+    fn set_nz(&mut self, value: u8)
+    {
+        self.set_flag(ZERO, value == 0);
+        self.set_flag(NEGATIVE, value & 0x80 != 0);
+    }
+
+    // push
+    // the stack is page one and grows down
+    fn push(&mut self, value: u8)
+    {
+        self.write(0x100 | self.registers.sp as u16, value);
+        self.registers.sp = self.registers.sp.wrapping_sub(1);
+    }
+
+    // pull
+    // the byte on top of the stack
+    fn pull(&mut self) -> u8
+    {
+        self.registers.sp = self.registers.sp.wrapping_add(1);
+        self.read(0x100 | self.registers.sp as u16)
+    }
+
+    // read
+    // the byte at the address
+    pub fn read(&self, address: u16) -> u8
+    {
+        self.memory[address as usize]
+    }
+
+    // read_word
+    // the little endian word at the address
+    pub fn read_word(&self, address: u16) -> u16
+    {
+        self.read(address) as u16 | (self.read(address.wrapping_add(1)) as u16) << 8
+    }
+
+    // read_zero_page_word
+    // a pointer in the zero page, $FF
+    // wraps around to $00 for its high byte
+    fn read_zero_page_word(&self, address: u8) -> u16
+    {
+        self.read(address as u16) as u16 | (self.read(address.wrapping_add(1) as u16) as u16) << 8
+    }
+
+    // write
+    // puts the byte at the address
+    pub fn write(&mut self, address: u16, value: u8)
+    {
+        self.memory[address as usize] = value;
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // simulator
+    // a 6502 with the code at $0600 and pc on it
+    fn simulator(code: &[u8]) -> Simulator
+    {
+        let mut simulator = Simulator::new(Cpu::Mos6502).unwrap();
+        simulator.load(0x0600, code);
+        simulator.registers.pc = 0x0600;
+        simulator
+    }
+
+    // cycles
+    // what each of the first steps took
+    fn cycles(simulator: &mut Simulator, steps: usize) -> Vec<u64>
+    {
+        (0..steps).map(|_| {
+            let before = simulator.cycles;
+            simulator.step().unwrap();
+            simulator.cycles - before
+        }).collect()
+    }
+
+    #[test]
+    fn decimal_adc()
+    {
+        // SED CLC LDA #$19 ADC #$28 BRK
+        let mut s = simulator(&[0xf8, 0x18, 0xa9, 0x19, 0x69, 0x28, 0x00]);
+        assert_eq!(s.run(None, 100), StopReason::Break);
+        assert_eq!(s.registers.a, 0x47);
+        assert_eq!(s.registers.status & CARRY, 0);
+
+        // SED CLC LDA #$99 ADC #$01 BRK carries out
+        let mut s = simulator(&[0xf8, 0x18, 0xa9, 0x99, 0x69, 0x01, 0x00]);
+        s.run(None, 100);
+        assert_eq!(s.registers.a, 0x00);
+        assert_eq!(s.registers.status & CARRY, CARRY);
+    }
+
+    #[test]
+    fn decimal_sbc()
+    {
+        // SED SEC LDA #$42 SBC #$15 BRK
+        let mut s = simulator(&[0xf8, 0x38, 0xa9, 0x42, 0xe9, 0x15, 0x00]);
+        s.run(None, 100);
+        assert_eq!(s.registers.a, 0x27);
+        assert_eq!(s.registers.status & CARRY, CARRY);
+
+        // SED SEC LDA #$15 SBC #$42 BRK borrows
+        let mut s = simulator(&[0xf8, 0x38, 0xa9, 0x15, 0xe9, 0x42, 0x00]);
+        s.run(None, 100);
+        assert_eq!(s.registers.a, 0x73);
+        assert_eq!(s.registers.status & CARRY, 0);
+
+        // SED SEC LDA #$50 SBC #$50 BRK is zero
+        let mut s = simulator(&[0xf8, 0x38, 0xa9, 0x50, 0xe9, 0x50, 0x00]);
+        s.run(None, 100);
+        assert_eq!(s.registers.a, 0x00);
+        assert_eq!(s.registers.status & (CARRY | ZERO | NEGATIVE), CARRY | ZERO);
+    }
+
+    #[test]
+    fn no_decimal_mode_on_the_2a03()
+    {
+        let mut s = Simulator::new(Cpu::Ricoh2A03).unwrap();
+        s.load(0x0600, &[0xf8, 0x18, 0xa9, 0x19, 0x69, 0x28, 0x00]);
+        s.registers.pc = 0x0600;
+        s.run(None, 100);
+        assert_eq!(s.registers.a, 0x41);
+    }
+
+    #[test]
+    fn only_the_6502_and_2a03_run()
+    {
+        for cpu in Cpu::all()
+        {
+            let runs = Simulator::new(cpu).is_ok();
+            assert_eq!(runs, matches!(cpu, Cpu::Mos6502 | Cpu::Ricoh2A03), "{}", cpu);
+        }
+        assert_eq!(Simulator::new(Cpu::Cmos65C02).err().unwrap().code, codes::UNSUPPORTED_CPU);
+    }
+
+    #[test]
+    fn page_crossing_reads_take_a_cycle_more()
+    {
+        // LDX #$01 LDA $0600,X LDA $06FF,X STA $06FF,X
+        let mut s = simulator(&[0xa2, 0x01, 0xbd, 0x00, 0x06, 0xbd, 0xff, 0x06, 0x9d, 0xff, 0x06]);
+        assert_eq!(cycles(&mut s, 4), vec![2, 4, 5, 5]);
+    }
+
+    #[test]
+    fn taken_branches_take_a_cycle_more_and_two_across_a_page()
+    {
+        // LDX #$00 BNE +0 BEQ +0 then a BEQ at $06FD over the page
+        let mut s = simulator(&[0xa2, 0x00, 0xd0, 0x00, 0xf0, 0x00]);
+        s.load(0x0606, &[0x4c, 0xfd, 0x06]);
+        s.load(0x06fd, &[0xf0, 0x01]);
+        assert_eq!(cycles(&mut s, 5), vec![2, 2, 3, 3, 4]);
+        assert_eq!(s.registers.pc, 0x0700);
+    }
+
+    #[test]
+    fn jsr_and_rts_use_the_stack()
+    {
+        // JSR $0610 BRK, $0610 RTS
+        let mut s = simulator(&[0x20, 0x10, 0x06, 0x00]);
+        s.load(0x0610, &[0x60]);
+
+        assert_eq!(cycles(&mut s, 1), vec![6]);
+        assert_eq!(s.registers.pc, 0x0610);
+        assert_eq!(s.registers.sp, 0xfb);
+        assert_eq!((s.read(0x01fd), s.read(0x01fc)), (0x06, 0x02));
+
+        assert_eq!(cycles(&mut s, 1), vec![6]);
+        assert_eq!(s.registers.pc, 0x0603);
+        assert_eq!(s.registers.sp, 0xfd);
+    }
+
+    #[test]
+    fn rti_pulls_the_status_then_pc()
+    {
+        let mut s = simulator(&[0x40]);
+        s.registers.sp = 0xfa;
+        s.load(0x01fb, &[NEGATIVE | BREAK | CARRY, 0x34, 0x12]);
+
+        assert_eq!(cycles(&mut s, 1), vec![6]);
+        assert_eq!(s.registers.pc, 0x1234);
+        assert_eq!(s.registers.sp, 0xfd);
+        assert_eq!(s.registers.status, NEGATIVE | UNUSED | CARRY);
+    }
+
+    #[test]
+    fn stop_reasons()
+    {
+        // INX BRK stops on the BRK without running it
+        let mut s = simulator(&[0xe8, 0x00]);
+        assert_eq!(s.run(None, 100), StopReason::Break);
+        assert_eq!(s.registers.pc, 0x0601);
+
+        // INX INX NOP stops at $0602
+        let mut s = simulator(&[0xe8, 0xe8, 0xea]);
+        assert_eq!(s.run(Some(0x0602), 100), StopReason::Address);
+        assert_eq!(s.registers.x, 2);
+
+        // JMP $0600 forever
+        let mut s = simulator(&[0x4c, 0x00, 0x06]);
+        assert_eq!(s.run(None, 30), StopReason::CycleLimit);
+        assert_eq!(s.cycles, 30);
+
+        // $02 jams an NMOS 6502
+        let mut s = simulator(&[0x02]);
+        assert_eq!(s.run(None, 100), StopReason::UnknownOpcode(0x02));
+
+        // a call comes back on its RTS
+        let mut s = simulator(&[0xe8, 0x60]);
+        s.registers.pc = 0x0700;
+        assert_eq!(s.call(0x0600, 100), StopReason::Returned);
+        assert_eq!((s.registers.pc, s.registers.sp, s.registers.x), (0x0700, 0xfd, 1));
+    }
+}
@@ -1,14 +1,16 @@
     use crate::assembler::insertable_num::InsertableNum;
     use crate::assembler::lexical_analyzer::*;
-    use crate::assembler::gen_errors::*;
+    use crate::assembler::diagnostic::*;
     use crate::assembler::Assembler;
+    use crate::assembler::warning::Warning;
     use std::num::Wrapping;
 
     // stack_math
     // does math between an operand stack and an operator stack
     // but only does it from a given index in the operator stack 
     // pops the left parenth off if the index given is one
-    pub fn stack_math(operand_stack:&mut Vec<InsertableNum>, operator_stack:&mut Vec<Token>) -> Result<(),GeneralError> 
+    // anything that had to be truncated goes on warnings
+    pub fn stack_math(operand_stack:&mut Vec<InsertableNum>, operator_stack:&mut Vec<Token>, warnings:&mut Vec<Diagnostic>) -> Result<(),Diagnostic> 
     {
         let mut index = 0;
 
@@ -51,7 +53,7 @@
                 // replace the number on the stack
                 let replaced_index = operand_1_index;
 
-                operand_stack[replaced_index as usize] = do_operation(*operand_1, *operand_2, operator.clone())?;
+                operand_stack[replaced_index as usize] = do_operation(*operand_1, *operand_2, operator.clone(), warnings)?;
 
                 // remove the next position 
                 operand_stack.remove(replaced_index as usize+1);
@@ -82,7 +84,7 @@
                 // replace the number on the stack
                 let replaced_index = operand_1_index;
                 
-                operand_stack[replaced_index as usize] = do_operation(*operand_1, *operand_2, operator.clone())?;
+                operand_stack[replaced_index as usize] = do_operation(*operand_1, *operand_2, operator.clone(), warnings)?;
 
                 // remove the next position 
                 operand_stack.remove(replaced_index as usize+1);
@@ -102,74 +104,57 @@
         // do_operation
     // takes the two operands 
     // and does the given operation
-    pub fn do_operation(operand1:InsertableNum, operand2:InsertableNum, operator:Token)-> Result<InsertableNum, GeneralError>
+    // results that don't fit wrap around with a warning
+    pub fn do_operation(operand1:InsertableNum, operand2:InsertableNum, operator:Token, warnings:&mut Vec<Diagnostic>)-> Result<InsertableNum, Diagnostic>
     {
-        match operator.token_type
+        let exact = match operator.token_type
         {
-            TokenType::PLUS =>
-            {
-                // return a u16
-                if operand1.is_two_bytes()|| operand2.is_two_bytes()
-                {
-                    return Ok(InsertableNum::TwoByte(operand1.unwrap_twobyte() + operand2.unwrap_twobyte()))
-                }      
-                else
-                {
-                    return Ok(InsertableNum::Byte((operand1.unwrap_byte() + operand2.unwrap_byte()) as u8))
-                }          
-            },
-            TokenType::MINUS =>
-            {
-                // return a u16
-                if operand1.is_two_bytes()|| operand2.is_two_bytes()
-                {
-                    let i =  Wrapping(operand1.unwrap_twobyte()) - Wrapping(operand2.unwrap_twobyte());
-                    return Ok(InsertableNum::TwoByte(i.0))
-                }      
-                else
-                {
-                    let i = Wrapping(operand1.unwrap_byte()) - Wrapping(operand2.unwrap_byte());
-                    return Ok(InsertableNum::Byte(i.0))
-                }          
-            },
-            TokenType::TIMES =>
+            TokenType::PLUS => operand1.unwrap() as i64 + operand2.unwrap() as i64,
+            TokenType::MINUS => operand1.unwrap() as i64 - operand2.unwrap() as i64,
+            TokenType::TIMES => operand1.unwrap() as i64 * operand2.unwrap() as i64,
+            _ => 0,
+        };
+
+        let result = do_wrapping_operation(operand1, operand2, operator.clone())?;
+
+        if operator.token_type != TokenType::DIVIDE && exact != result.unwrap() as i64
+        {
+            let message = match result.size()
             {
-                // return a u16
-                if operand1.is_two_bytes()|| operand2.is_two_bytes()
-                {
-                    let i =  Wrapping(operand1.unwrap_twobyte()) * Wrapping(operand2.unwrap_twobyte());
-                    return Ok(InsertableNum::TwoByte(i.0))
-                }      
-                else
-                {
-                    let i = Wrapping(operand1.unwrap_byte()) * Wrapping(operand2.unwrap_byte());
-                    return Ok(InsertableNum::Byte(i.0))
-                }          
-            }, 
+                1 => format!("Value {} doesn't fit in 1 byte, truncated to ${:02X}", exact, result.unwrap()),
+                2 => format!("Value {} doesn't fit in 2 bytes, truncated to ${:04X}", exact, result.unwrap()),
+                _ => format!("Value {} doesn't fit in 3 bytes, truncated to ${:06X}", exact, result.unwrap()),
+            };
+            warnings.push(Diagnostic::warning(Warning::ValueTruncated, &message, &operator));
+        }
+
+        Ok(result)
+    }
+
+    // do_wrapping_operation
+    // does the operation in the size of
+    // the bigger operand wrapping around
+    fn do_wrapping_operation(operand1:InsertableNum, operand2:InsertableNum, operator:Token)-> Result<InsertableNum, Diagnostic>
+    {
+        let size = operand1.size().max(operand2.size());
+        let (a, b) = (Wrapping(operand1.unwrap()), Wrapping(operand2.unwrap()));
+
+        let result = match operator.token_type
+        {
+            TokenType::PLUS => a + b,
+            TokenType::MINUS => a - b,
+            TokenType::TIMES => a * b,
             TokenType::DIVIDE =>
             {
-
                 // cannot divide by zero
-                if operand2.unwrap() == 0
+                if b.0 == 0
                 {
-                    return Err(Assembler::create_error("Cannot divide by zero", &operator, vec![]));
+                    return Err(Assembler::create_error(codes::DIVIDE_BY_ZERO, "Cannot divide by zero", &operator, vec![]));
                 }
-
-
-                // return a u16
-                if operand1.is_two_bytes()|| operand2.is_two_bytes()
-                {
-                    let i =  Wrapping(operand1.unwrap_twobyte()) / Wrapping(operand2.unwrap_twobyte());
-                    return Ok(InsertableNum::TwoByte(i.0));
-                }      
-                else
-                {
-                    let i = Wrapping(operand1.unwrap_byte()) /  Wrapping(operand2.unwrap_byte());
-                    return Ok(InsertableNum::Byte(i.0))
-                }          
+                a / b
             }
-            _ => {Ok(InsertableNum::Byte(0))}
-        }
-    }
-
+            _ => return Ok(InsertableNum::Byte(0)),
+        };
 
+        Ok(InsertableNum::sized(result.0, size))
+    }
@@ -0,0 +1,233 @@
+
+use crate::assembler::insertable_num::InsertableNum;
+use crate::assembler::object_code::ObjectCode;
+
+
+// SymbolFormat
+// the label file formats emulators
+// and debuggers can read
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum SymbolFormat
+{
+    Vice,       // VICE monitor, al C:1234 .label
+    Mesen,      // Mesen .mlb, P:0000:label
+    Fceux,      // FCEUX .nl, $C000#label#
+    Plain,      // name = $1234
+}
+
+impl SymbolFormat
+{
+    // from_name
+    // turns a name given on the
+    // command line into a symbol format
+    pub fn from_name(name: &str) -> Option<SymbolFormat>
+    {
+        match name.to_lowercase().as_str()
+        {
+            "vice" => Some(SymbolFormat::Vice),
+            "mesen" | "mlb" => Some(SymbolFormat::Mesen),
+            "fceux" | "nl" => Some(SymbolFormat::Fceux),
+            "plain" | "sym" => Some(SymbolFormat::Plain),
+            _ => None,
+        }
+    }
+}
+
+
+// Symbol
+// a label from the symbol table,
+// constants are labels set with = and
+// don't point at anything in memory
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct Symbol
+{
+    pub name: String,
+    pub value: InsertableNum,
+    pub constant: bool,
+    pub line: u32,          // the file line it was defined on
+}
+
+
+// symbol_file
+// writes the symbols in the given format, only
+// the plain format lists the constants since the
+// others would label their values as addresses
+pub fn symbol_file(format: SymbolFormat, symbols: &[Symbol], object_code: &ObjectCode) -> String
+{
+    let mut out = String::new();
+
+    for symbol in symbols
+    {
+        let address = symbol.value.unwrap();
+
+        match format
+        {
+            SymbolFormat::Plain =>
+            {
+                match symbol.value
+                {
+                    InsertableNum::Byte(num) => out += &format!("{} = ${:02X}", symbol.name, num),
+                    InsertableNum::TwoByte(num) => out += &format!("{} = ${:04X}", symbol.name, num),
+                    InsertableNum::ThreeByte(num) => out += &format!("{} = ${:06X}", symbol.name, num),
+                }
+                if symbol.constant
+                {
+                    out += " ; constant";
+                }
+                out += "\n";
+            }
+            _ if symbol.constant => {}
+            SymbolFormat::Vice => out += &format!("al C:{:04X} .{}\n", address, symbol.name),
+            SymbolFormat::Fceux => out += &format!("${:04X}#{}#\n", address, symbol.name),
+            SymbolFormat::Mesen =>
+            {
+                let (memory, offset) = mesen_memory_type(address, object_code);
+                out += &format!("{}:{:04X}:{}\n", memory, offset, symbol.name);
+            }
+        }
+    }
+
+    out
+}
+
+// mesen_memory_type
+// mesen labels are relative to the memory they are in,
+// PRG-ROM labels are offsets into the rom and the
+// rest are cpu addresses, a 16K rom is mirrored
+// at $8000 and $C000 so both land on the same offset
+fn mesen_memory_type(address: u32, object_code: &ObjectCode) -> (char, u32)
+{
+    let prg_size = object_code.ines.prg_banks.clamp(1, 2) as u32 * 0x4000;
+
+    if address < 0x2000
+    {
+        ('R', address & 0x7ff)
+    }
+    else if address < 0x6000
+    {
+        ('G', address)
+    }
+    else if address < 0x8000
+    {
+        ('S', address - 0x6000)
+    }
+    else
+    {
+        ('P', (address - 0x8000) % prg_size)
+    }
+}
+
+// read_symbols
+// reads back a symbol file in any of the formats
+// but mesen's, whose PRG-ROM offsets don't say
+// where the rom sits, lines it doesn't know are skipped
+pub fn read_symbols(text: &str) -> Vec<(String, u32)>
+{
+    let mut symbols = vec![];
+
+    for line in text.lines()
+    {
+        let line = line.trim();
+
+        let symbol = if let Some(rest) = line.strip_prefix("al ")
+        {
+            // al C:1234 .label
+            rest.split_once(' ').and_then(|(address, name)|
+            {
+                let address = address.trim_start_matches("C:");
+                Some((name.trim().trim_start_matches('.').to_string(), u32::from_str_radix(address, 16).ok()?))
+            })
+        }
+        else if let Some(rest) = line.strip_prefix('$')
+        {
+            // $C000#label#
+            let mut parts = rest.split('#');
+            match (parts.next(), parts.next())
+            {
+                (Some(address), Some(name)) => u32::from_str_radix(address, 16).ok().map(|address| (name.to_string(), address)),
+                _ => None,
+            }
+        }
+        else
+        {
+            // name = $1234 ; constant
+            let line = line.split(';').next().unwrap_or("");
+            line.split_once('=').and_then(|(name, value)|
+            {
+                let value = value.trim().strip_prefix('$')?;
+                Some((name.trim().to_string(), u32::from_str_radix(value, 16).ok()?))
+            })
+        };
+
+        match symbol
+        {
+            Some((name, address)) if !name.is_empty() && !name.contains(char::is_whitespace) => symbols.push((name, address)),
+            _ => (),
+        }
+    }
+
+    symbols
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    // symbols
+    // a code label, a zero page
+    // label and an = constant
+    fn symbols() -> Vec<Symbol>
+    {
+        vec![
+            Symbol { name: "start".to_string(), value: InsertableNum::TwoByte(0xc000), constant: false, line: 0 },
+            Symbol { name: "counter".to_string(), value: InsertableNum::Byte(0x10), constant: false, line: 0 },
+            Symbol { name: "lives".to_string(), value: InsertableNum::Byte(3), constant: true, line: 0 },
+        ]
+    }
+
+    #[test]
+    fn vice()
+    {
+        assert_eq!(symbol_file(SymbolFormat::Vice, &symbols(), &ObjectCode::new()), "al C:C000 .start\nal C:0010 .counter\n");
+    }
+
+    #[test]
+    fn fceux()
+    {
+        assert_eq!(symbol_file(SymbolFormat::Fceux, &symbols(), &ObjectCode::new()), "$C000#start#\n$0010#counter#\n");
+    }
+
+    #[test]
+    fn plain_lists_the_constants()
+    {
+        assert_eq!(symbol_file(SymbolFormat::Plain, &symbols(), &ObjectCode::new()), "start = $C000\ncounter = $10\nlives = $03 ; constant\n");
+    }
+
+    #[test]
+    fn mesen_memory_types()
+    {
+        let labels: Vec<Symbol> = [("ram", 0x0810), ("ppu", 0x2000), ("save", 0x6004), ("code", 0xc010), ("vector", 0xfffa)].iter()
+            .map(|(name, address)| Symbol { name: name.to_string(), value: InsertableNum::TwoByte(*address), constant: false, line: 0 })
+            .collect();
+
+        let text = symbol_file(SymbolFormat::Mesen, &labels, &ObjectCode::new());
+        assert_eq!(text, "R:0010:ram\nG:2000:ppu\nS:0004:save\nP:0010:code\nP:3FFA:vector\n");
+
+        // one bank is mirrored at $8000
+        let mirror = [Symbol { name: "mirror".to_string(), value: InsertableNum::TwoByte(0x8010), constant: false, line: 0 }];
+        assert_eq!(symbol_file(SymbolFormat::Mesen, &mirror, &ObjectCode::new()), "P:0010:mirror\n");
+
+        // with two banks PRG-ROM starts at $8000
+        let mut object_code = ObjectCode::new();
+        object_code.ines.prg_banks = 2;
+        assert_eq!(symbol_file(SymbolFormat::Mesen, &labels[3..], &object_code), "P:4010:code\nP:7FFA:vector\n");
+        assert!(!symbol_file(SymbolFormat::Mesen, &symbols(), &object_code).contains("lives"));
+    }
+}
@@ -0,0 +1,368 @@
+use std::collections::HashMap;
+use crate::assembler::Assembler;
+use crate::assembler::diagnostic::{Diagnostic, codes};
+use crate::assembler::options::AssemblerOptions;
+use crate::assembler::simulator::{Simulator, StopReason, CARRY, ZERO, INTERRUPT, DECIMAL, OVERFLOW, NEGATIVE};
+
+
+// the most cycles a JSR in a test can take
+// before it is taken to never come back
+pub const TEST_CYCLE_LIMIT: u64 = 1_000_000;
+
+
+// TestBlock
+// where a .TEST block is in the
+// file, the lines between are its body
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(PartialEq)]
+pub struct TestBlock
+{
+    pub name: String,
+    pub line: u32,          // the .TEST line
+    pub end_line: u32,      // the .ENDTEST line
+}
+
+// TestResult
+// how a test went, failure is the
+// first line of it that didn't work
+#[derive(Debug)]
+#[derive(Clone)]
+pub struct TestResult
+{
+    pub name: String,
+    pub file: String,
+    pub line: u32,
+    pub cycles: u64,        // what its JSRs took
+    pub failure: Option<Diagnostic>,
+}
+
+impl TestResult
+{
+    // passed
+    // true if every line of it worked
+    pub fn passed(&self) -> bool
+    {
+        self.failure.is_none()
+    }
+}
+
+
+// Target
+// what a test line sets or expects
+enum Target
+{
+    A,
+    X,
+    Y,
+    SP,
+    P,
+    Flag(u8),
+    Memory(u16),
+}
+
+// Statement
+// one line of a test body
+enum Statement
+{
+    Set(Target, u32),
+    Call(u16),
+    Expect(Target, u32, String),    // the target as written, for the message
+}
+
+
+// run_tests
+// assembles the file and runs each of its .TEST blocks
+// on a fresh simulator with the object code loaded,
+// the assembly errors come back if it didn't assemble
+pub fn run_tests(file_name: &str, options: AssemblerOptions) -> Result<Vec<TestResult>, Vec<Diagnostic>>
+{
+    let mut assembler = Assembler::without_output(file_name, options).map_err(|err| vec![err])?;
+    assembler.run()?;
+
+    let source = match std::fs::read_to_string(file_name)
+    {
+        Ok(source) => source,
+        Err(err) => return Err(vec![Diagnostic::new(codes::IO, &format!("Couldn't read {}: {}", file_name, err), "Test")]),
+    };
+    let lines: Vec<&str> = source.lines().collect();
+
+    let symbols: HashMap<String, u32> = assembler.symbol_table.iter().map(|(name, value)| (name.to_string(), value.unwrap())).collect();
+
+    let mut results = vec![];
+    for test in &assembler.tests
+    {
+        // the cpu the file ended up on, it may have a .CPU
+        let mut simulator = Simulator::new(assembler.cpu).map_err(|err| vec![err])?;
+        for segment in &assembler.object_code.segments
+        {
+            simulator.load(segment.start as u16, &segment.bytes);
+        }
+
+        let mut failure = None;
+        for line in test.line + 1..test.end_line
+        {
+            let text = lines.get(line as usize - 1).copied().unwrap_or("");
+            if let Err(mut diagnostic) = run_line(text, &symbols, &mut simulator)
+            {
+                diagnostic.file = Some(file_name.to_string());
+                diagnostic.line = line;
+                failure = Some(diagnostic);
+                break;
+            }
+        }
+
+        results.push(TestResult { name: test.name.to_string(), file: file_name.to_string(), line: test.line, cycles: simulator.cycles, failure });
+    }
+
+    Ok(results)
+}
+
+// run_line
+// does a line of a test body, an
+// error if it can't be read or failed
+fn run_line(text: &str, symbols: &HashMap<String, u32>, simulator: &mut Simulator) -> Result<(), Diagnostic>
+{
+    // comments are ; like everywhere else
+    let text = text.split(';').next().unwrap_or("").trim();
+    if text.is_empty()
+    {
+        return Ok(());
+    }
+
+    match parse_statement(text, symbols)?
+    {
+        Statement::Set(target, value) =>
+        {
+            let registers = &mut simulator.registers;
+            match target
+            {
+                Target::A => registers.a = value as u8,
+                Target::X => registers.x = value as u8,
+                Target::Y => registers.y = value as u8,
+                Target::SP => registers.sp = value as u8,
+                Target::P => registers.status = value as u8,
+                Target::Flag(flag) if value == 1 => registers.status |= flag,
+                Target::Flag(flag) => registers.status &= !flag,
+                Target::Memory(address) => simulator.write(address, value as u8),
+            }
+        }
+        Statement::Call(address) =>
+        {
+            match simulator.call(address, TEST_CYCLE_LIMIT)
+            {
+                StopReason::Returned => (),
+                reason => return Err(Diagnostic::new(codes::TEST_FAILED, &format!("The JSR didn't return, it stopped at {} with {}", reason, simulator.registers), "Test")),
+            }
+        }
+        Statement::Expect(target, expected, written) =>
+        {
+            let registers = &simulator.registers;
+            let (found, size) = match target
+            {
+                Target::A => (registers.a as u32, 1),
+                Target::X => (registers.x as u32, 1),
+                Target::Y => (registers.y as u32, 1),
+                Target::SP => (registers.sp as u32, 1),
+                Target::P => (registers.status as u32, 1),
+                Target::Flag(flag) => ((registers.status & flag != 0) as u32, 0),
+                Target::Memory(address) => (simulator.read(address) as u32, 1),
+            };
+
+            if found != expected
+            {
+                let show = |value: u32| if size == 0 { value.to_string() } else { format!("${:02X}", value) };
+                return Err(Diagnostic::new(codes::TEST_FAILED, &format!("{} is {}, expected {}", written, show(found), show(expected)), "Test"));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// parse_statement
+// JSR routine, EXPECT target = value
+// or target = value to set it
+fn parse_statement(text: &str, symbols: &HashMap<String, u32>) -> Result<Statement, Diagnostic>
+{
+    let (word, rest) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+
+    if word.eq_ignore_ascii_case("jsr")
+    {
+        let address = parse_value(rest.trim(), symbols)?;
+        return match u16::try_from(address)
+        {
+            Ok(address) => Ok(Statement::Call(address)),
+            Err(_) => Err(Diagnostic::new(codes::VALUE_OUT_OF_RANGE, &format!("${:X} isn't a 16 bit address", address), "Test")),
+        };
+    }
+
+    let expect = word.eq_ignore_ascii_case("expect");
+    let assignment = if expect { rest } else { text };
+
+    let (left, right) = match assignment.split_once('=')
+    {
+        Some(parts) => parts,
+        None => return Err(Diagnostic::new(codes::SYNTAX, &format!("Expected JSR, EXPECT or an =, found {}", text), "Test")),
+    };
+
+    let target = parse_target(left.trim(), symbols)?;
+    let value = parse_value(right.trim(), symbols)?;
+
+    let max = match target
+    {
+        Target::Flag(_) => 1,
+        _ => 0xff,
+    };
+    if value > max
+    {
+        return Err(Diagnostic::new(codes::VALUE_OUT_OF_RANGE, &format!("{} doesn't fit in {}, it can be 0 to {}", value, left.trim(), max), "Test"));
+    }
+
+    if expect
+    {
+        Ok(Statement::Expect(target, value, left.trim().to_string()))
+    }
+    else
+    {
+        Ok(Statement::Set(target, value))
+    }
+}
+
+// parse_target
+// a register, a flag, or
+// the memory at an address
+fn parse_target(text: &str, symbols: &HashMap<String, u32>) -> Result<Target, Diagnostic>
+{
+    let target = match text.to_uppercase().as_str()
+    {
+        "A" => Target::A,
+        "X" => Target::X,
+        "Y" => Target::Y,
+        "SP" => Target::SP,
+        "P" => Target::P,
+        "C" => Target::Flag(CARRY),
+        "Z" => Target::Flag(ZERO),
+        "I" => Target::Flag(INTERRUPT),
+        "D" => Target::Flag(DECIMAL),
+        "V" => Target::Flag(OVERFLOW),
+        "N" => Target::Flag(NEGATIVE),
+        _ =>
+        {
+            let address = parse_value(text, symbols)?;
+            match u16::try_from(address)
+            {
+                Ok(address) => Target::Memory(address),
+                Err(_) => return Err(Diagnostic::new(codes::VALUE_OUT_OF_RANGE, &format!("${:X} isn't a 16 bit address", address), "Test")),
+            }
+        }
+    };
+
+    Ok(target)
+}
+
+// parse_value
+// $ff hex, %101 binary, decimal or a label, with
+// + and - after it and < or > in front for the low
+// or high byte the way .BYTE values are written
+fn parse_value(text: &str, symbols: &HashMap<String, u32>) -> Result<u32, Diagnostic>
+{
+    if let Some(rest) = text.strip_prefix('<')
+    {
+        return Ok(parse_value(rest, symbols)? & 0xff);
+    }
+    if let Some(rest) = text.strip_prefix('>')
+    {
+        return Ok((parse_value(rest, symbols)? >> 8) & 0xff);
+    }
+
+    // the last + or - splits it
+    if let Some(split) = text.rfind(['+', '-'])
+    {
+        if split > 0
+        {
+            let left = parse_value(text[..split].trim(), symbols)?;
+            let right = parse_value(text[split + 1..].trim(), symbols)?;
+            return Ok(if text[split..].starts_with('+') { left.wrapping_add(right) } else { left.wrapping_sub(right) });
+        }
+    }
+
+    let number = if let Some(hex) = text.strip_prefix('$')
+    {
+        u32::from_str_radix(hex, 16).ok()
+    }
+    else if let Some(binary) = text.strip_prefix('%')
+    {
+        u32::from_str_radix(binary, 2).ok()
+    }
+    else if text.starts_with(|c: char| c.is_ascii_digit())
+    {
+        text.parse::<u32>().ok()
+    }
+    else
+    {
+        match symbols.get(text)
+        {
+            Some(value) => return Ok(*value),
+            None => return Err(Diagnostic::new(codes::UNDEFINED_LABEL, &format!("Label {} not defined", text), "Test")),
+        }
+    };
+
+    number.ok_or_else(|| Diagnostic::new(codes::SYNTAX, &format!("Expected a number or a label, found {}", text), "Test"))
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    fn symbols() -> HashMap<String, u32>
+    {
+        HashMap::from([("table".to_string(), 0x1234), ("add3".to_string(), 0x0600)])
+    }
+
+    #[test]
+    fn values()
+    {
+        let symbols = symbols();
+        assert_eq!(parse_value("$ff", &symbols).unwrap(), 0xff);
+        assert_eq!(parse_value("%101", &symbols).unwrap(), 5);
+        assert_eq!(parse_value("42", &symbols).unwrap(), 42);
+        assert_eq!(parse_value("table", &symbols).unwrap(), 0x1234);
+        assert_eq!(parse_value("table+2", &symbols).unwrap(), 0x1236);
+        assert_eq!(parse_value("table - 4 + 1", &symbols).unwrap(), 0x1231);
+        assert_eq!(parse_value("<table", &symbols).unwrap(), 0x34);
+        assert_eq!(parse_value(">table", &symbols).unwrap(), 0x12);
+        assert_eq!(parse_value(">table+1", &symbols).unwrap(), 0x12);
+
+        assert_eq!(parse_value("nothing", &symbols).unwrap_err().code, codes::UNDEFINED_LABEL);
+        assert_eq!(parse_value("$fg", &symbols).unwrap_err().code, codes::SYNTAX);
+    }
+
+    #[test]
+    fn statements()
+    {
+        let symbols = symbols();
+        assert!(matches!(parse_statement("JSR add3", &symbols), Ok(Statement::Call(0x0600))));
+        assert!(matches!(parse_statement("jsr $0610", &symbols), Ok(Statement::Call(0x0610))));
+        assert!(matches!(parse_statement("A = $05", &symbols), Ok(Statement::Set(Target::A, 5))));
+        assert!(matches!(parse_statement("sp = $ff", &symbols), Ok(Statement::Set(Target::SP, 0xff))));
+        assert!(matches!(parse_statement("C = 1", &symbols), Ok(Statement::Set(Target::Flag(CARRY), 1))));
+        assert!(matches!(parse_statement("$10 = <table", &symbols), Ok(Statement::Set(Target::Memory(0x10), 0x34))));
+        assert!(matches!(parse_statement("table+1 = 7", &symbols), Ok(Statement::Set(Target::Memory(0x1235), 7))));
+
+        match parse_statement("EXPECT N = 0", &symbols)
+        {
+            Ok(Statement::Expect(Target::Flag(NEGATIVE), 0, written)) => assert_eq!(written, "N"),
+            _ => panic!("EXPECT N = 0 wasn't an expect of the N flag"),
+        }
+
+        let error = |text: &str| parse_statement(text, &symbols).err().map(|e| e.code);
+        assert_eq!(error("A = $100"), Some(codes::VALUE_OUT_OF_RANGE));
+        assert_eq!(error("C = 2"), Some(codes::VALUE_OUT_OF_RANGE));
+        assert_eq!(error("JSR $10000"), Some(codes::VALUE_OUT_OF_RANGE));
+        assert_eq!(error("LDA #$05"), Some(codes::SYNTAX));
+        assert_eq!(error("EXPECT Q = 1"), Some(codes::UNDEFINED_LABEL));
+    }
+}
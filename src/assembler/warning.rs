@@ -0,0 +1,153 @@
+
+use crate::assembler::diagnostic::codes;
+
+
+// Warning
+// the kinds of warnings the assembler gives,
+// each one can be turned on, off or into an error
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+#[derive(Eq)]
+#[derive(Hash)]
+pub enum Warning
+{
+    ImplicitUpcast,         // a 1 byte value used where only a 2 byte mode fits
+    UnusedLabel,            // a label nothing refers to
+    ValueTruncated,         // a value that doesn't fit and had its top cut off
+    BranchRange,            // a branch within a few bytes of how far it can reach
+    LabelShadowsMnemonic,   // a label named like an instruction of another 6502
+    NoDecimalMode,          // SED on a cpu without decimal mode
+}
+
+// WarningLevel
+// what happens when a warning comes up
+#[derive(Debug)]
+#[derive(Clone)]
+#[derive(Copy)]
+#[derive(PartialEq)]
+pub enum WarningLevel
+{
+    Allow,  // say nothing
+    Warn,   // report it and carry on
+    Deny,   // report it as an error
+}
+
+impl Warning
+{
+    // all
+    // every warning there is
+    pub fn all() -> Vec<Warning>
+    {
+        vec![
+            Warning::ImplicitUpcast,
+            Warning::UnusedLabel,
+            Warning::ValueTruncated,
+            Warning::BranchRange,
+            Warning::LabelShadowsMnemonic,
+            Warning::NoDecimalMode,
+        ]
+    }
+
+    // name
+    // the name used on the command line,
+    // i.e. -Wno-implicit-upcast
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Warning::ImplicitUpcast => "implicit-upcast",
+            Warning::UnusedLabel => "unused-label",
+            Warning::ValueTruncated => "value-truncated",
+            Warning::BranchRange => "branch-range",
+            Warning::LabelShadowsMnemonic => "label-shadows-mnemonic",
+            Warning::NoDecimalMode => "no-decimal-mode",
+        }
+    }
+
+    // from_name
+    // the warning with the given name if there is one
+    pub fn from_name(name: &str) -> Option<Warning>
+    {
+        Warning::all().into_iter().find(|w| w.name() == name)
+    }
+
+    // code
+    // the diagnostic code it is reported with
+    pub fn code(&self) -> &'static str
+    {
+        match self
+        {
+            Warning::ImplicitUpcast => codes::IMPLICIT_UPCAST,
+            Warning::UnusedLabel => codes::UNUSED_LABEL,
+            Warning::ValueTruncated => codes::VALUE_TRUNCATED,
+            Warning::BranchRange => codes::BRANCH_RANGE,
+            Warning::LabelShadowsMnemonic => codes::LABEL_SHADOWS_MNEMONIC,
+            Warning::NoDecimalMode => codes::NO_DECIMAL_MODE,
+        }
+    }
+
+    // from_code
+    // the warning a diagnostic code belongs to
+    pub fn from_code(code: &str) -> Option<Warning>
+    {
+        Warning::all().into_iter().find(|w| w.code() == code)
+    }
+
+    // default_level
+    // unused labels are off unless asked for, lots
+    // of code defines every hardware register it might use
+    pub fn default_level(&self) -> WarningLevel
+    {
+        match self
+        {
+            Warning::UnusedLabel => WarningLevel::Allow,
+            _ => WarningLevel::Warn,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+    use crate::assembler::options::AssemblerOptions;
+
+    #[test]
+    fn names_and_codes_go_both_ways()
+    {
+        for warning in Warning::all()
+        {
+            assert_eq!(Warning::from_name(warning.name()), Some(warning));
+            assert_eq!(Warning::from_code(warning.code()), Some(warning));
+        }
+
+        assert_eq!(Warning::from_name("branch-range"), Some(Warning::BranchRange));
+        assert_eq!(Warning::from_name("no-such-warning"), None);
+        assert_eq!(Warning::from_code(codes::SYNTAX), None);
+    }
+
+    #[test]
+    fn unused_labels_are_off_by_default()
+    {
+        let options = AssemblerOptions::default();
+        assert_eq!(options.warning_level(Warning::UnusedLabel), WarningLevel::Allow);
+        assert_eq!(options.warning_level(Warning::BranchRange), WarningLevel::Warn);
+    }
+
+    #[test]
+    fn the_last_switch_wins_and_werror_denies_what_is_on()
+    {
+        let mut options = AssemblerOptions::default();
+        options.warnings = vec![(Warning::BranchRange, WarningLevel::Allow), (Warning::BranchRange, WarningLevel::Warn), (Warning::ImplicitUpcast, WarningLevel::Allow)];
+        assert_eq!(options.warning_level(Warning::BranchRange), WarningLevel::Warn);
+
+        options.warnings_as_errors = true;
+        assert_eq!(options.warning_level(Warning::BranchRange), WarningLevel::Deny);
+        assert_eq!(options.warning_level(Warning::ImplicitUpcast), WarningLevel::Allow);
+        assert_eq!(options.warning_level(Warning::UnusedLabel), WarningLevel::Allow);
+    }
+}
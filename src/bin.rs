@@ -1,21 +1,599 @@
-use asm_6502_lib::run;
+use asm_6502_lib::{run_with_options, test_with_options, instruction_reference, disassemble_file, read_symbols, DisassemblerOptions, Opcode, Simulator, AssemblerOptions, MessageFormat, OutputFormat, HexAddressing, SymbolFormat, Warning, WarningLevel, Cpu};
 use std::env;
+use std::io::IsTerminal;
 
 fn main() {
 
     let args: Vec<String> = env::args().collect();
 
+    if args.get(1).map(|arg| arg.as_str()) == Some("disasm")
+    {
+        disasm(&args[2..]);
+        return;
+    }
+
+    if args.get(1).map(|arg| arg.as_str()) == Some("run")
+    {
+        run(&args[2..]);
+        return;
+    }
+
+    let mut options = AssemblerOptions::default();
+    let mut files: Vec<String> = vec![];
+
+    // intel hex settings, applied after
+    // everything is read so flag order doesn't matter
+    let mut hex_record_length: Option<u8> = None;
+    let mut hex_segment = false;
+
+    // prg settings
+    let mut basic_stub: Option<u16> = None;
+
+    // print the instruction table instead of assembling
+    let mut instruction_table = false;
+
+    // colors by default only when someone is looking
+    // and hasn't asked for none, see no-color.org
+    options.color = std::io::stdout().is_terminal() && env::var_os("NO_COLOR").is_none();
+
+    let mut i = 1;
+    while i < args.len()
+    {
+        match args[i].as_str()
+        {
+            "-f" | "--format" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|name| OutputFormat::from_name(name))
+                {
+                    Some(format) => options.output_format = format,
+                    None =>
+                    {
+                        println!("Expected an output format after {}: bin, hex, srec, prg, nes, nes2, apple or applesingle", args[i-1]);
+                        return;
+                    }
+                }
+            }
+            "--hex-record-length" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|n| n.parse::<u8>().ok())
+                {
+                    Some(n) if n > 0 => hex_record_length = Some(n),
+                    _ =>
+                    {
+                        println!("Expected a record length between 1 and 255 after --hex-record-length");
+                        return;
+                    }
+                }
+            }
+            "--hex-segment" => hex_segment = true,
+            "--basic-stub" => basic_stub = basic_stub.or(Some(0x0801)),
+            "--basic-start" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|n| parse_number(n))
+                {
+                    Some(n) if n <= 0xffff => basic_stub = Some(n as u16),
+                    _ =>
+                    {
+                        println!("Expected an address after --basic-start, i.e. $1001");
+                        return;
+                    }
+                }
+            }
+            "-l" | "--listing" =>
+            {
+                i += 1;
+                match args.get(i)
+                {
+                    Some(name) => options.listing_file = Some(name.to_string()),
+                    None =>
+                    {
+                        println!("Expected a listing file after {}", args[i-1]);
+                        return;
+                    }
+                }
+            }
+            "--symbols" =>
+            {
+                i += 2;
+                match (args.get(i-1).and_then(|name| SymbolFormat::from_name(name)), args.get(i))
+                {
+                    (Some(format), Some(name)) => options.symbol_files.push((format, name.to_string())),
+                    _ =>
+                    {
+                        println!("Expected a symbol format (vice, mesen, fceux or plain) and a file after --symbols");
+                        return;
+                    }
+                }
+            }
+            "--map" | "--map-json" =>
+            {
+                i += 1;
+                match args.get(i)
+                {
+                    Some(name) if args[i-1] == "--map" => options.map_file = Some(name.to_string()),
+                    Some(name) => options.map_json_file = Some(name.to_string()),
+                    None =>
+                    {
+                        println!("Expected a file after {}", args[i-1]);
+                        return;
+                    }
+                }
+            }
+            "--io" =>
+            {
+                i += 1;
+                let range = args.get(i).and_then(|r| r.split_once('-')).and_then(|(start, end)| Some((parse_number(start)?, parse_number(end)?)));
+                match range
+                {
+                    Some(range) => options.io_ranges.push(range),
+                    None =>
+                    {
+                        println!("Expected an address range after --io, i.e. $D000-$DFFF");
+                        return;
+                    }
+                }
+            }
+            "-g" | "--debug-info" =>
+            {
+                i += 1;
+                match args.get(i)
+                {
+                    Some(name) => options.debug_info_file = Some(name.to_string()),
+                    None =>
+                    {
+                        println!("Expected a debug info file after {}", args[i-1]);
+                        return;
+                    }
+                }
+            }
+            "--max-errors" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|n| n.parse::<usize>().ok())
+                {
+                    Some(n) => options.max_errors = n,
+                    None =>
+                    {
+                        println!("Expected a number after --max-errors, 0 for no limit");
+                        return;
+                    }
+                }
+            }
+            "--color" =>
+            {
+                i += 1;
+                match args.get(i).map(|s| s.as_str())
+                {
+                    Some("always") => options.color = true,
+                    Some("never") => options.color = false,
+                    Some("auto") => (),
+                    _ =>
+                    {
+                        println!("Expected always, never or auto after --color");
+                        return;
+                    }
+                }
+            }
+            "--cpu" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|name| Cpu::from_name(name))
+                {
+                    Some(cpu) => options.cpu = cpu,
+                    None =>
+                    {
+                        println!("Expected a cpu after --cpu: 6502, 6502x, 65c02, r65c02, w65c02, 65816, 2a03 or huc6280");
+                        return;
+                    }
+                }
+            }
+            "--instructions" =>
+            {
+                i += 1;
+                match args.get(i)
+                {
+                    Some(name) => options.instruction_file = Some(name.to_string()),
+                    None =>
+                    {
+                        println!("Expected an instruction table file after --instructions");
+                        return;
+                    }
+                }
+            }
+            "--instruction-table" => instruction_table = true,
+            "--chr" =>
+            {
+                i += 1;
+                match args.get(i)
+                {
+                    Some(name) => options.chr_file = Some(name.to_string()),
+                    None =>
+                    {
+                        println!("Expected a CHR file after --chr");
+                        return;
+                    }
+                }
+            }
+            "--module-name" =>
+            {
+                i += 1;
+                match args.get(i)
+                {
+                    Some(name) => options.module_name = Some(name.to_string()),
+                    None =>
+                    {
+                        println!("Expected a name after --module-name");
+                        return;
+                    }
+                }
+            }
+            flag if flag.starts_with("--message-format") =>
+            {
+                // --message-format=json or --message-format json
+                let format = match flag.strip_prefix("--message-format=")
+                {
+                    Some(format) => Some(format),
+                    None if flag == "--message-format" => { i += 1; args.get(i).map(|s| s.as_str()) },
+                    None => None,
+                };
+
+                match format
+                {
+                    Some("human") => options.message_format = MessageFormat::Human,
+                    Some("json") => options.message_format = MessageFormat::Json,
+                    _ =>
+                    {
+                        println!("Expected human or json after --message-format");
+                        return;
+                    }
+                }
+            }
+            "-Werror" => options.warnings_as_errors = true,
+            "-Wall" => options.warnings.extend(Warning::all().into_iter().map(|w| (w, WarningLevel::Warn))),
+            flag if flag.starts_with("-W") =>
+            {
+                // -Wname turns a warning on, -Wno-name off
+                // and -Werror=name makes it an error
+                let (name, level) = if let Some(name) = flag.strip_prefix("-Werror=")
+                {
+                    (name, WarningLevel::Deny)
+                }
+                else if let Some(name) = flag.strip_prefix("-Wno-")
+                {
+                    (name, WarningLevel::Allow)
+                }
+                else
+                {
+                    (&flag[2..], WarningLevel::Warn)
+                };
+
+                match Warning::from_name(name)
+                {
+                    Some(warning) => options.warnings.push((warning, level)),
+                    None =>
+                    {
+                        let names: Vec<&str> = Warning::all().iter().map(|w| w.name()).collect();
+                        println!("Unknown warning {}, expected one of: {}", name, names.join(", "));
+                        return;
+                    }
+                }
+            }
+            _ => files.push(args[i].to_string()),
+        }
+        i += 1;
+    }
+
+    if let OutputFormat::IntelHex { record_length, addressing } = &mut options.output_format
+    {
+        if let Some(n) = hex_record_length
+        {
+            *record_length = n;
+        }
+        if hex_segment
+        {
+            *addressing = HexAddressing::Segment;
+        }
+    }
+
+    if let OutputFormat::Prg { basic_stub: stub } = &mut options.output_format
+    {
+        *stub = basic_stub;
+    }
+
+    if instruction_table
+    {
+        match instruction_reference(options.cpu, options.instruction_file.as_deref())
+        {
+            Ok(reference) => print!("{}", reference),
+            Err(diagnostic) => println!("{}", diagnostic.render(None, options.color)),
+        }
+        return;
+    }
+
+    // asm_6502 test file.asm runs its .TEST blocks
+    if files.len() == 2 && files[0] == "test"
+    {
+        let (text, passed) = test_with_options(&files[1], options);
+        println!("{}", text);
+        if !passed
+        {
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // if args aren't big enough return
-    if args.len() < 3
+    if files.len() < 2
     {
         println!("Expected 2 arguments: input file name and output file name");
         return;
     }
 
-    let file_name = &args[1];
-    let out_put = &args[2];
-    
+    let file_name = &files[0];
+    let out_put = &files[1];
 
-    println!("{}", run(file_name, out_put));
+
+    let (text, success) = run_with_options(file_name, out_put, options);
+    println!("{}", text);
+    if !success
+    {
+        std::process::exit(1);
+    }
+}
+
+// disasm
+// the disasm subcommand, writes the source
+// for a binary file to a file or stdout
+fn disasm(args: &[String])
+{
+    let mut options = DisassemblerOptions::default();
+    let mut files: Vec<String> = vec![];
+
+    let mut i = 0;
+    while i < args.len()
+    {
+        match args[i].as_str()
+        {
+            "--origin" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|n| parse_number(n))
+                {
+                    Some(n) => options.origin = n,
+                    None =>
+                    {
+                        println!("Expected an address after --origin, i.e. $8000");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--cpu" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|name| Cpu::from_name(name))
+                {
+                    Some(cpu) => options.cpu = cpu,
+                    None =>
+                    {
+                        println!("Expected a cpu after --cpu: 6502, 6502x, 65c02, r65c02, w65c02, 65816, 2a03 or huc6280");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--instructions" =>
+            {
+                i += 1;
+                match args.get(i).map(|name| Opcode::load(name))
+                {
+                    Some(Ok(opcodes)) => options.extra_opcodes = opcodes,
+                    Some(Err(diagnostic)) =>
+                    {
+                        println!("{}", diagnostic.render(None, false));
+                        std::process::exit(1);
+                    }
+                    None =>
+                    {
+                        println!("Expected an instruction table file after --instructions");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--symbols" =>
+            {
+                i += 1;
+                match args.get(i).map(|name| (name, std::fs::read_to_string(name)))
+                {
+                    Some((_, Ok(text))) => options.symbols.extend(read_symbols(&text)),
+                    Some((name, Err(err))) =>
+                    {
+                        println!("Couldn't read {}: {}", name, err);
+                        std::process::exit(1);
+                    }
+                    None =>
+                    {
+                        println!("Expected a symbol file after --symbols");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--data" =>
+            {
+                i += 1;
+                let range = args.get(i).and_then(|r| r.split_once('-')).and_then(|(start, end)| Some((parse_number(start)?, parse_number(end)?)));
+                match range
+                {
+                    Some(range) => options.data_ranges.push(range),
+                    None =>
+                    {
+                        println!("Expected an address range after --data, i.e. $FFFA-$FFFF");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--trace" => options.trace = true,
+            "--entry" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|n| parse_number(n))
+                {
+                    Some(n) =>
+                    {
+                        options.entry_points.push(n);
+                        options.trace = true;
+                    }
+                    None =>
+                    {
+                        println!("Expected an address after --entry, i.e. $8000");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            _ => files.push(args[i].to_string()),
+        }
+        i += 1;
+    }
+
+    if files.is_empty()
+    {
+        println!("Expected a binary file to disassemble and optionally an output file name");
+        std::process::exit(1);
+    }
+
+    match (disassemble_file(&files[0], &options), files.get(1))
+    {
+        (Ok(source), Some(out_put)) =>
+        {
+            if let Err(err) = std::fs::write(out_put, source)
+            {
+                println!("Couldn't write {}: {}", out_put, err);
+                std::process::exit(1);
+            }
+        }
+        (Ok(source), None) => print!("{}", source),
+        (Err(diagnostic), _) =>
+        {
+            println!("{}", diagnostic.render(None, false));
+            std::process::exit(1);
+        }
+    }
+}
+
+// run
+// the run subcommand, loads a binary into the
+// simulator and runs it until it stops
+fn run(args: &[String])
+{
+    let mut files: Vec<String> = vec![];
+    let mut cpu = Cpu::Mos6502;
+    let mut load: u32 = 0;
+    let mut start: Option<u32> = None;
+    let mut until: Option<u32> = None;
+    let mut max_cycles: u64 = 10_000_000;
+
+    let mut i = 0;
+    while i < args.len()
+    {
+        match args[i].as_str()
+        {
+            flag @ ("--load" | "--start" | "--until") =>
+            {
+                i += 1;
+                match args.get(i).and_then(|n| parse_number(n))
+                {
+                    Some(n) if n <= 0xffff && flag == "--load" => load = n,
+                    Some(n) if n <= 0xffff && flag == "--start" => start = Some(n),
+                    Some(n) if n <= 0xffff => until = Some(n),
+                    _ =>
+                    {
+                        println!("Expected an address after {}, i.e. $0600", flag);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--max-cycles" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|n| n.parse::<u64>().ok())
+                {
+                    Some(n) => max_cycles = n,
+                    None =>
+                    {
+                        println!("Expected a number after --max-cycles");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "--cpu" =>
+            {
+                i += 1;
+                match args.get(i).and_then(|name| Cpu::from_name(name))
+                {
+                    Some(c) => cpu = c,
+                    None =>
+                    {
+                        println!("Expected a cpu after --cpu: 6502, 6502x, 65c02, r65c02, w65c02, 65816, 2a03 or huc6280");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            _ => files.push(args[i].to_string()),
+        }
+        i += 1;
+    }
+
+    let bytes = match files.first().map(|name| (name, std::fs::read(name)))
+    {
+        Some((_, Ok(bytes))) => bytes,
+        Some((name, Err(err))) =>
+        {
+            println!("Couldn't read {}: {}", name, err);
+            std::process::exit(1);
+        }
+        None =>
+        {
+            println!("Expected a binary file to run");
+            std::process::exit(1);
+        }
+    };
+
+    let mut simulator = match Simulator::new(cpu)
+    {
+        Ok(simulator) => simulator,
+        Err(err) =>
+        {
+            println!("{}", err.render(None, false));
+            std::process::exit(1);
+        }
+    };
+    simulator.load(load as u16, &bytes);
+
+    // start at the reset vector if the
+    // binary has it and no start was given
+    match start
+    {
+        Some(start) => simulator.registers.pc = start as u16,
+        None if load <= 0xfffc && load as usize + bytes.len() >= 0xfffe => simulator.reset(),
+        None => simulator.registers.pc = load as u16,
+    }
+
+    let reason = simulator.run(until.map(|n| n as u16), max_cycles);
+
+    println!("Stopped at {} after {} cycles", reason, simulator.cycles);
+    println!("{}", simulator.registers);
 }
 
+// parse_number
+// reads a number given on the command line,
+// $ff and 0xff are hex, anything else is decimal
+fn parse_number(text: &str) -> Option<u32>
+{
+    if let Some(hex) = text.strip_prefix('$').or(text.strip_prefix("0x"))
+    {
+        return u32::from_str_radix(hex, 16).ok();
+    }
+
+    text.parse::<u32>().ok()
+}
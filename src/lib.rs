@@ -1,9 +1,27 @@
+// diagnostics carry everything a tool needs to point at a problem,
+// they are only built when something goes wrong so their size doesn't matter
+#![allow(clippy::result_large_err)]
+
 
 mod assembler;
 use crate::assembler::Assembler;
+pub use crate::assembler::options::{AssemblerOptions, MessageFormat};
+pub use crate::assembler::output_format::{OutputFormat, HexAddressing};
+pub use crate::assembler::symbol_file::{SymbolFormat, read_symbols};
+pub use crate::assembler::diagnostic::{Diagnostic, Severity, Summary, codes};
+pub use crate::assembler::warning::{Warning, WarningLevel};
+pub use crate::assembler::cpu::Cpu;
+pub use crate::assembler::instruction::{AddressingMode, Opcode};
+use crate::assembler::instruction::Instruction;
+pub use crate::assembler::simulator::{Simulator, Registers, StopReason};
+pub use crate::assembler::test_runner::{run_tests, TestResult};
+pub use crate::assembler::disassembler::{disassemble, disassembly_source, trace, DisassemblerOptions, DisassembledLine, LineKind, Operand, OperandKind};
+pub use crate::assembler::{Token, TokenType};
 use std::os::raw::c_char;
 use std::ffi::CString;
 use core::ffi::CStr;
+use std::collections::HashMap;
+use std::fs;
 
 static mut STRING_POINTER: *mut c_char = 0 as *mut c_char;
 
@@ -43,30 +61,137 @@ pub extern fn free_string() {
 
 pub fn run(file_name: &String, out_put: &String) -> String
 {
+    run_with_options(file_name, out_put, AssemblerOptions::default()).0
+}
 
-    let result;
-    let ass_result = Assembler::new(file_name, out_put);
+// run_with_options
+// same as run but lets you pick the output
+// format and the like, true if it assembled
+pub fn run_with_options(file_name: &str, out_put: &str, options: AssemblerOptions) -> (String, bool)
+{
 
+    let max_errors = options.max_errors;
+    let color = options.color;
+    let message_format = options.message_format;
 
-    if let Err(err) = ass_result 
+    let (diagnostics, success) = match assemble_file(file_name, out_put, options)
     {
-        result = Err(err);
+        Ok(warnings) => (warnings, true),
+        Err(diagnostics) => (diagnostics, false),
+    };
+
+    let summary = Summary::new(&diagnostics, success, max_errors);
+
+    // one json object per line, the summary last
+    if message_format == MessageFormat::Json
+    {
+        let mut lines: Vec<String> = diagnostics.iter().map(|d| d.json()).collect();
+        lines.push(summary.json());
+        return (lines.join("\n"), success);
     }
-    else 
-    {   
-        // we know its not an error
-        // so it can just be unwraped
-        result = ass_result.unwrap().run();
+
+    // the source of each file a diagnostic points
+    // at, read once so the line can be shown
+    let mut sources: HashMap<String, Option<String>> = HashMap::new();
+
+    let mut returned = String::new();
+    for diagnostic in &diagnostics
+    {
+        let source = match &diagnostic.file
+        {
+            Some(file) => sources.entry(file.to_string()).or_insert_with(|| fs::read_to_string(file).ok()).as_deref(),
+            None => None,
+        };
+        returned += &format!("{}\n", diagnostic.render(source, color));
     }
 
-    match result 
+    (returned + &summary.text(), success)
+}
+
+// test_with_options
+// runs the .TEST blocks of a file and says how
+// each went, or why it didn't assemble, and
+// true only if it assembled and they all passed
+pub fn test_with_options(file_name: &str, options: AssemblerOptions) -> (String, bool)
+{
+    let color = options.color;
+    let max_errors = options.max_errors;
+    let source = fs::read_to_string(file_name).ok();
+
+    let results = match run_tests(file_name, options)
     {
-        Err(err) =>
+        Ok(results) => results,
+        Err(diagnostics) =>
+        {
+            let mut returned = String::new();
+            for diagnostic in &diagnostics
+            {
+                returned += &format!("{}\n", diagnostic.render(source.as_deref(), color));
+            }
+            return (returned + &Summary::new(&diagnostics, false, max_errors).text(), false);
+        }
+    };
+
+    let mut returned = String::new();
+    for result in &results
+    {
+        match &result.failure
         {
-            let returned = format!("ERROR: \n{}", err.to_string());
-            returned
-        },
+            None => returned += &format!("PASS {} ({}:{}, {} cycles)\n", result.name, result.file, result.line, result.cycles),
+            Some(failure) =>
+            {
+                returned += &format!("FAIL {} ({}:{})\n", result.name, result.file, result.line);
+                returned += &format!("{}\n", failure.render(source.as_deref(), color));
+            }
+        }
+    }
+
+    let passed = results.iter().filter(|r| r.passed()).count();
+    (returned + &format!("{} tests, {} passed, {} failed", results.len(), passed, results.len() - passed), passed == results.len())
+}
 
-        _ => {return "Success!".to_string()}
+// instruction_reference
+// a markdown table of the instructions the cpu
+// has, with the rows of an instruction table file on top
+pub fn instruction_reference(cpu: Cpu, instruction_file: Option<&str>) -> Result<String, Diagnostic>
+{
+    let extra = match instruction_file
+    {
+        Some(file) => Opcode::load(file)?,
+        None => vec![],
+    };
+
+    Ok(Instruction::reference(cpu, &extra))
+}
+
+// disassemble_file
+// the source for a binary file, the bytes start
+// at the options' origin and are traced first if asked
+pub fn disassemble_file(file_name: &str, options: &DisassemblerOptions) -> Result<String, Diagnostic>
+{
+    match fs::read(file_name)
+    {
+        Ok(bytes) =>
+        {
+            let options = if options.trace { trace(&bytes, options) } else { options.clone() };
+            Ok(disassembly_source(&disassemble(&bytes, &options), &options))
+        }
+        Err(err) =>
+        {
+            let mut diagnostic = Diagnostic::new(codes::IO, &format!("Couldn't read {}: {}", file_name, err), "Disassembler");
+            diagnostic.file = Some(file_name.to_string());
+            Err(diagnostic)
+        }
     }
-}
\ No newline at end of file
+}
+
+// assemble_file
+// assembles a file into out_put and hands back
+// the diagnostics so they can be looked at
+// instead of printed, the warnings if it worked
+// and everything if it didn't
+pub fn assemble_file(file_name: &str, out_put: &str, options: AssemblerOptions) -> Result<Vec<Diagnostic>, Vec<Diagnostic>>
+{
+    let mut assembler = Assembler::new(file_name, out_put, options).map_err(|err| vec![err])?;
+    assembler.run()
+}